@@ -0,0 +1,43 @@
+// 各个 bench 共用的分配器，统计分配次数和字节数，realloc 也算一次分配
+#![allow(dead_code)]
+
+use std::{
+  alloc::{GlobalAlloc, Layout, System},
+  sync::atomic::{AtomicUsize, Ordering},
+};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+    System.alloc(layout)
+  }
+
+  unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+    System.realloc(ptr, layout, new_size)
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    System.dealloc(ptr, layout)
+  }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+// 到目前为止的分配次数
+pub fn allocations() -> usize {
+  ALLOCATIONS.load(Ordering::Relaxed)
+}
+
+// 到目前为止分配的总字节数
+pub fn allocated() -> usize {
+  ALLOCATED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,56 @@
+// cargo bench --bench reuse
+mod common;
+
+use std::{hint::black_box, time::Instant};
+
+use json_parser::{Json, ReusableParser};
+
+const MESSAGES: usize = 100_000;
+
+fn bench(name: &str, inputs: &[String], mut parse: impl FnMut(&str) -> Json) {
+  let allocations = common::allocations();
+  let start = Instant::now();
+  for input in inputs {
+    black_box(parse(black_box(input)));
+  }
+  let elapsed = start.elapsed();
+  let allocations = common::allocations() - allocations;
+
+  println!(
+    "{:<16} {:>8} messages {:>10} allocations {:>10.2?}",
+    name,
+    inputs.len(),
+    allocations,
+    elapsed
+  );
+}
+
+fn main() {
+  let inputs = (0..MESSAGES)
+    .map(|i| {
+      format!(
+        "{{\"id\": {}, \"ok\": true, \"values\": [1, 2, 3, null]}}",
+        i
+      )
+    })
+    .collect::<Vec<String>>();
+
+  bench("Json::parse", &inputs, |input| Json::parse(input).unwrap());
+
+  let mut parser = ReusableParser::new();
+  bench("ReusableParser", &inputs, |input| {
+    parser.parse(input).unwrap()
+  });
+
+  // 10 万个元素的大数组，紧凑格式的输入重新输出后应与原文一致
+  let numbers = (0..100_000)
+    .map(|i| i.to_string())
+    .collect::<Vec<String>>()
+    .join(",");
+  let large = [format!("[{}]", numbers)];
+  assert_eq!(
+    Json::parse(&large[0]).unwrap().to_string().unwrap(),
+    large[0]
+  );
+  bench("large array", &large, |input| Json::parse(input).unwrap());
+}
@@ -0,0 +1,55 @@
+// cargo bench --bench tokenizer
+mod common;
+
+use std::{hint::black_box, time::Instant};
+
+use json_parser::tokenizer::Tokenizer;
+
+const ITERATIONS: usize = 20;
+
+fn bench(name: &str, input: &str) {
+  let allocated = common::allocated();
+  let tokens = Tokenizer::new(input).tokenize().unwrap();
+  let allocated = common::allocated() - allocated;
+  let count = tokens.len();
+  drop(tokens);
+
+  let start = Instant::now();
+  for _ in 0..ITERATIONS {
+    black_box(Tokenizer::new(black_box(input)).tokenize().unwrap());
+  }
+  let elapsed = start.elapsed() / ITERATIONS as u32;
+
+  println!(
+    "{:<16} {:>8} bytes {:>8} tokens {:>10} bytes allocated {:>10.2?}/iter",
+    name,
+    input.len(),
+    count,
+    allocated,
+    elapsed
+  );
+}
+
+fn main() {
+  let objects = (0..20_000)
+    .map(|i| format!("{{\"id\": {}, \"name\": \"item {}\", \"ok\": true}}", i, i))
+    .collect::<Vec<String>>()
+    .join(",\n");
+  bench("ascii objects", &format!("[{}]", objects));
+
+  let strings = (0..20_000)
+    .map(|i| format!("\"wörld 日本 {}\"", i))
+    .collect::<Vec<String>>()
+    .join(", ");
+  bench("unicode strings", &format!("[{}]", strings));
+
+  // 字面量探测（true/false/null）在长输入末尾也应是 O(1)
+  let literals = ["true", "false", "null", "0"]
+    .iter()
+    .cycle()
+    .take(200_000)
+    .copied()
+    .collect::<Vec<&str>>()
+    .join(",");
+  bench("flat literals", &format!("[{}]", literals));
+}
@@ -0,0 +1,279 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+  parser::{ArrayAst, Ast, BoolAst, NullAst, NumberAst, ObjectAst, PropertyAst, StringAst},
+  span::Span,
+};
+
+impl From<&str> for Ast {
+  fn from(value: &str) -> Self {
+    Ast::from(value.to_string())
+  }
+}
+
+impl From<String> for Ast {
+  fn from(value: String) -> Self {
+    Ast::String(StringAst {
+      value,
+      span: Span::default(),
+    })
+  }
+}
+
+impl From<f64> for Ast {
+  fn from(value: f64) -> Self {
+    Ast::Number(NumberAst {
+      value,
+      raw: None,
+      span: Span::default(),
+    })
+  }
+}
+
+impl From<i64> for Ast {
+  fn from(value: i64) -> Self {
+    Ast::Number(NumberAst {
+      value: value as f64,
+      raw: Some(value.to_string()),
+      span: Span::default(),
+    })
+  }
+}
+
+impl From<bool> for Ast {
+  fn from(value: bool) -> Self {
+    Ast::Boolean(BoolAst {
+      value,
+      span: Span::default(),
+    })
+  }
+}
+
+impl From<()> for Ast {
+  fn from(_: ()) -> Self {
+    Ast::Null(NullAst {
+      span: Span::default(),
+    })
+  }
+}
+
+impl From<Vec<Ast>> for Ast {
+  fn from(value: Vec<Ast>) -> Self {
+    value.into_iter().collect()
+  }
+}
+
+impl FromIterator<Ast> for Ast {
+  fn from_iter<T: IntoIterator<Item = Ast>>(iter: T) -> Self {
+    Ast::Array(ArrayAst {
+      value: iter.into_iter().map(Box::new).collect(),
+      span: Span::default(),
+    })
+  }
+}
+
+impl FromIterator<(String, Ast)> for Ast {
+  fn from_iter<T: IntoIterator<Item = (String, Ast)>>(iter: T) -> Self {
+    Ast::Object(ObjectAst {
+      value: iter
+        .into_iter()
+        .map(|(key, value)| PropertyAst::new(key, value))
+        .collect(),
+      span: Span::default(),
+    })
+  }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ConvertError {
+  // 变体不匹配，如把字符串转成 bool
+  TypeMismatch {
+    expected: &'static str,
+    found: &'static str,
+  },
+  // 数字不是精确的整数，或超出 i64 范围
+  NotAnInteger(f64),
+}
+
+impl fmt::Display for ConvertError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ConvertError::TypeMismatch { expected, found } => {
+        write!(f, "Expected {}, found {}", expected, found)
+      }
+      ConvertError::NotAnInteger(value) => write!(f, "{} is not an integer", value),
+    }
+  }
+}
+
+fn mismatch(expected: &'static str, found: &Ast) -> ConvertError {
+  ConvertError::TypeMismatch {
+    expected,
+    found: found.type_name(),
+  }
+}
+
+impl TryFrom<Ast> for String {
+  type Error = ConvertError;
+
+  fn try_from(ast: Ast) -> Result<Self, Self::Error> {
+    match ast {
+      Ast::String(ast) => Ok(ast.value),
+      _ => Err(mismatch("string", &ast)),
+    }
+  }
+}
+
+impl TryFrom<Ast> for f64 {
+  type Error = ConvertError;
+
+  fn try_from(ast: Ast) -> Result<Self, Self::Error> {
+    match ast {
+      Ast::Number(ast) => Ok(ast.value),
+      _ => Err(mismatch("number", &ast)),
+    }
+  }
+}
+
+impl TryFrom<Ast> for i64 {
+  type Error = ConvertError;
+
+  fn try_from(ast: Ast) -> Result<Self, Self::Error> {
+    let value = f64::try_from(ast)?;
+
+    // i64::MAX as f64 会进位到 2^63，所以上界用开区间
+    if value.fract() == 0.0 && value >= i64::MIN as f64 && value < i64::MAX as f64 {
+      Ok(value as i64)
+    } else {
+      Err(ConvertError::NotAnInteger(value))
+    }
+  }
+}
+
+impl TryFrom<Ast> for bool {
+  type Error = ConvertError;
+
+  fn try_from(ast: Ast) -> Result<Self, Self::Error> {
+    match ast {
+      Ast::Boolean(ast) => Ok(ast.value),
+      _ => Err(mismatch("boolean", &ast)),
+    }
+  }
+}
+
+impl TryFrom<Ast> for Vec<Ast> {
+  type Error = ConvertError;
+
+  fn try_from(ast: Ast) -> Result<Self, Self::Error> {
+    match ast {
+      Ast::Array(ast) => Ok(ast.value.into_iter().map(|item| *item).collect()),
+      _ => Err(mismatch("array", &ast)),
+    }
+  }
+}
+
+// 重复的 key 以最后一个为准，与 ObjectAst::as_map 一致
+impl TryFrom<Ast> for HashMap<String, Ast> {
+  type Error = ConvertError;
+
+  fn try_from(ast: Ast) -> Result<Self, Self::Error> {
+    match ast {
+      Ast::Object(ast) => Ok(
+        ast
+          .value
+          .into_iter()
+          .map(|property| (property.key.value.value, *property.value))
+          .collect(),
+      ),
+      _ => Err(mismatch("object", &ast)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_from_scalars() {
+    assert_eq!(Ast::from("a"), Ast::from("a".to_string()));
+    assert!(matches!(Ast::from(1.5), Ast::Number(n) if n.value == 1.5));
+    assert!(matches!(Ast::from(-2i64), Ast::Number(n) if n.value == -2.0));
+    assert!(matches!(Ast::from(true), Ast::Boolean(b) if b.value));
+    assert!(matches!(Ast::from(()), Ast::Null(_)));
+  }
+
+  #[test]
+  fn test_from_iter_and_serialize() {
+    let ast = Ast::from(vec![
+      Ast::from("hello"),
+      Ast::from(1i64),
+      Ast::from(()),
+      vec![("a".to_string(), Ast::from(true))]
+        .into_iter()
+        .collect(),
+    ]);
+
+    assert_eq!(ast.to_string().unwrap(), "[\"hello\",1,null,{\"a\":true}]");
+  }
+
+  #[test]
+  fn test_try_from() {
+    let json = |input: &str| crate::Json::parse(input).unwrap();
+
+    assert_eq!(String::try_from(json("\"hi\"")), Ok("hi".to_string()));
+    assert_eq!(f64::try_from(json("1.5")), Ok(1.5));
+    assert_eq!(i64::try_from(json("-42")), Ok(-42));
+    assert_eq!(i64::try_from(json("1e3")), Ok(1000));
+    assert_eq!(bool::try_from(json("true")), Ok(true));
+
+    let items = Vec::<Ast>::try_from(json("[1, \"a\"]")).unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[1].as_str(), Some("a"));
+
+    let map = HashMap::<String, Ast>::try_from(json("{\"a\": 1, \"b\": 2, \"a\": 3}")).unwrap();
+    assert_eq!(map.len(), 2);
+    assert_eq!(map["a"].as_f64(), Some(3.0));
+  }
+
+  #[test]
+  fn test_try_from_mismatch() {
+    let json = |input: &str| crate::Json::parse(input).unwrap();
+    let mismatch = |expected, found| ConvertError::TypeMismatch { expected, found };
+
+    assert_eq!(
+      String::try_from(json("1")),
+      Err(mismatch("string", "number"))
+    );
+    assert_eq!(f64::try_from(json("null")), Err(mismatch("number", "null")));
+    assert_eq!(
+      i64::try_from(json("\"1\"")),
+      Err(mismatch("number", "string"))
+    );
+    assert_eq!(
+      bool::try_from(json("[]")),
+      Err(mismatch("boolean", "array"))
+    );
+    assert_eq!(
+      Vec::<Ast>::try_from(json("{}")),
+      Err(mismatch("array", "object"))
+    );
+    assert_eq!(
+      HashMap::<String, Ast>::try_from(json("[]")),
+      Err(mismatch("object", "array"))
+    );
+
+    assert_eq!(
+      i64::try_from(json("1.5")),
+      Err(ConvertError::NotAnInteger(1.5))
+    );
+    assert_eq!(
+      i64::try_from(json("1e19")),
+      Err(ConvertError::NotAnInteger(1e19))
+    );
+    assert_eq!(
+      mismatch("string", "number").to_string(),
+      "Expected string, found number"
+    );
+  }
+}
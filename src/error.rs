@@ -0,0 +1,367 @@
+use std::{error::Error, fmt, io, path::PathBuf};
+
+use crate::span::{Loc, Span};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseErrorKind {
+  Syntax,
+  UnexpectedEof,
+  // 输入为空或只有空白、注释，与写了一半的文档区分开
+  EmptyInput,
+  InvalidUtf8 { byte_offset: usize },
+  // 超出 ParseOptions 中的长度限制
+  LimitExceeded,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+  pub kind: ParseErrorKind,
+  pub message: String,
+  pub span: Option<Span>,
+}
+
+impl ParseError {
+  pub fn new(message: impl Into<String>, span: Span) -> Self {
+    Self {
+      kind: ParseErrorKind::Syntax,
+      message: message.into(),
+      span: Some(span),
+    }
+  }
+
+  // span 指向最后一个 token，输入为空时没有位置
+  pub fn eof(span: Option<Span>) -> Self {
+    Self {
+      kind: ParseErrorKind::UnexpectedEof,
+      message: "Unexpected end of input".to_string(),
+      span,
+    }
+  }
+
+  pub fn empty_input() -> Self {
+    Self {
+      kind: ParseErrorKind::EmptyInput,
+      message: "Empty input".to_string(),
+      span: None,
+    }
+  }
+
+  pub fn limit_exceeded(message: impl Into<String>, span: Option<Span>) -> Self {
+    Self {
+      kind: ParseErrorKind::LimitExceeded,
+      message: message.into(),
+      span,
+    }
+  }
+
+  pub fn invalid_utf8(byte_offset: usize, span: Span) -> Self {
+    Self {
+      kind: ParseErrorKind::InvalidUtf8 { byte_offset },
+      message: format!("Invalid UTF-8 sequence at byte {}", byte_offset),
+      span: Some(span),
+    }
+  }
+}
+
+impl ParseError {
+  // 像 rustc 一样显示出错的那一行，并在出错位置下面画 ^。
+  // 对同一份输入显示多个错误时，用 SourceLines 避免每次重新查找行首
+  pub fn snippet(&self, source: &str) -> String {
+    SourceLines::new(source).snippet(self)
+  }
+}
+
+// 缓存 source 每一行的起始位置
+pub struct SourceLines<'a> {
+  source: &'a str,
+  starts: Vec<usize>,
+}
+
+impl<'a> SourceLines<'a> {
+  pub fn new(source: &'a str) -> Self {
+    Self {
+      source,
+      starts: line_starts(source),
+    }
+  }
+
+  // 与 ParseError::snippet 相同，span 的行号不在 source 范围内时返回空字符串
+  pub fn snippet(&self, error: &ParseError) -> String {
+    let source = self.source;
+    let Some(span) = &error.span else {
+      return String::new();
+    };
+
+    let Some(&line_start) = span
+      .start
+      .line
+      .checked_sub(1)
+      .and_then(|index| self.starts.get(index))
+    else {
+      return String::new();
+    };
+    let line_start = skip_bom(source, span.start.line, line_start);
+    let text = source[line_start..]
+      .split(['\r', '\n'])
+      .next()
+      .unwrap_or_default();
+    let before = chars_before(source, line_start, &span.start);
+
+    // 跨行的 span 只标到行尾
+    let width = if span.end.line == span.start.line {
+      span.end.offset.saturating_sub(span.start.offset).max(1)
+    } else {
+      (text.chars().count() + 1).saturating_sub(before).max(1)
+    };
+    // 保留 tab，保证 ^ 和原文对齐
+    let padding = text
+      .chars()
+      .take(before)
+      .map(|c| if c == '\t' { '\t' } else { ' ' })
+      .collect::<String>();
+
+    let line_no = span.start.line.to_string();
+    let gutter = " ".repeat(line_no.len());
+
+    format!(
+      "{} | {}\n{} | {}{}",
+      line_no,
+      text,
+      gutter,
+      padding,
+      "^".repeat(width)
+    )
+  }
+}
+
+// 先显示错误信息，下一行接着显示 snippet，没有位置时只有错误信息
+pub struct ErrorWithSource<'a> {
+  error: &'a ParseError,
+  source: &'a str,
+}
+
+impl ParseError {
+  pub fn with_source<'a>(&'a self, source: &'a str) -> ErrorWithSource<'a> {
+    ErrorWithSource {
+      error: self,
+      source,
+    }
+  }
+}
+
+impl<'a> fmt::Display for ErrorWithSource<'a> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let snippet = self.error.snippet(self.source);
+
+    if snippet.is_empty() {
+      write!(f, "{}", self.error)
+    } else {
+      write!(f, "{}\n{}", self.error, snippet)
+    }
+  }
+}
+
+// 第一行开头的 BOM 不属于行内容
+pub(crate) fn skip_bom(source: &str, line: usize, line_start: usize) -> usize {
+  if line == 1 && source.starts_with('\u{FEFF}') {
+    '\u{FEFF}'.len_utf8()
+  } else {
+    line_start
+  }
+}
+
+// loc 在所在行中前面有几个字符。按 offset 而不是列号计算，列号可能按 tab_width 展开过
+pub(crate) fn chars_before(source: &str, line_start: usize, loc: &Loc) -> usize {
+  loc
+    .offset
+    .saturating_sub(source[..line_start].chars().count())
+}
+
+// 每一行起始处的字节位置，换行规则与 Tokenizer 一致
+pub(crate) fn line_starts(source: &str) -> Vec<usize> {
+  let mut starts = vec![0];
+  let bytes = source.as_bytes();
+  let mut i = 0;
+
+  while i < bytes.len() {
+    match bytes[i] {
+      b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+        i += 2;
+        starts.push(i);
+      }
+      b'\r' | b'\n' => {
+        i += 1;
+        starts.push(i);
+      }
+      _ => i += 1,
+    }
+  }
+
+  starts
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.span {
+      Some(span) => write!(
+        f,
+        "{} at line {}, column {}",
+        self.message, span.start.line, span.start.column
+      ),
+      None => write!(f, "{}", self.message),
+    }
+  }
+}
+
+impl Error for ParseError {}
+
+// 方便在返回 io::Result 的函数中用 ? 传递解析错误
+impl From<ParseError> for io::Error {
+  fn from(error: ParseError) -> Self {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+  }
+}
+
+// Json::parse_file 的错误，解析失败时带上文件路径
+#[derive(Debug)]
+pub enum JsonError {
+  Io(io::Error),
+  Parse { path: PathBuf, error: ParseError },
+}
+
+impl fmt::Display for JsonError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      JsonError::Io(error) => write!(f, "{}", error),
+      JsonError::Parse { path, error } => write!(f, "{}: {}", path.display(), error),
+    }
+  }
+}
+
+impl Error for JsonError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      JsonError::Io(error) => Some(error),
+      JsonError::Parse { error, .. } => Some(error),
+    }
+  }
+}
+
+impl From<io::Error> for JsonError {
+  fn from(error: io::Error) -> Self {
+    JsonError::Io(error)
+  }
+}
+
+// 读文件的错误原样返回，解析错误转成 InvalidData
+impl From<JsonError> for io::Error {
+  fn from(error: JsonError) -> Self {
+    match error {
+      JsonError::Io(error) => error,
+      error => io::Error::new(io::ErrorKind::InvalidData, error),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::error::Error;
+
+  use crate::{Json, JsonError, ParseError, SourceLines, Span};
+
+  #[test]
+  fn test_snippet() {
+    let source = "{\n  \"a\" 1,\n  \"b\": 2\n}";
+    let err = Json::parse(source).unwrap_err();
+
+    assert_eq!(err.snippet(source), "2 |   \"a\" 1,\n  |       ^");
+
+    let source = "[\r\n\t\"x\", nullable]";
+    let err = Json::parse(source).unwrap_err();
+    assert_eq!(
+      err.snippet(source),
+      "2 | \t\"x\", nullable]\n  | \t     ^^^^^^^^"
+    );
+
+    assert_eq!(Json::parse("").unwrap_err().snippet(""), "");
+
+    // 手动构造的 span 行号可能是 0 或超出范围
+    let err = ParseError::new("x", Span::default());
+    assert_eq!(err.snippet("[1]"), "");
+    let mut span = Span::default();
+    span.start.line = 5;
+    assert_eq!(ParseError::new("x", span).snippet("[1]"), "");
+  }
+
+  #[test]
+  fn test_source_lines() {
+    let source = "[1 2,\n 3 4]";
+    let (_, errors) = Json::parse_recovering(source);
+    let lines = SourceLines::new(source);
+
+    let snippets = errors
+      .iter()
+      .map(|error| lines.snippet(error))
+      .collect::<Vec<String>>();
+    assert_eq!(snippets, ["1 | [1 2,\n  |    ^", "2 |  3 4]\n  |    ^"]);
+    assert_eq!(snippets[1], errors[1].snippet(source));
+  }
+
+  #[test]
+  fn test_with_source() {
+    let source = "[1,\n 2 3]";
+    let err = Json::parse(source).unwrap_err();
+
+    assert_eq!(
+      err.with_source(source).to_string(),
+      "Unexpected number at line 2, column 4\n2 |  2 3]\n  |    ^"
+    );
+    assert_eq!(
+      Json::parse("").unwrap_err().with_source("").to_string(),
+      "Empty input"
+    );
+  }
+
+  #[test]
+  fn test_error_trait() {
+    fn parse(input: &str) -> Result<Json, Box<dyn Error>> {
+      Ok(Json::parse(input)?)
+    }
+
+    assert!(parse("[1]").is_ok());
+    let err = parse("[1,").unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "Unexpected end of input at line 1, column 3"
+    );
+    assert!(err.downcast_ref::<crate::ParseError>().is_some());
+
+    let err = Json::parse_file("does/not/exist.json").unwrap_err();
+    let source = err.source().unwrap();
+    assert!(source.downcast_ref::<std::io::Error>().is_some());
+    assert!(matches!(err, JsonError::Io(_)));
+  }
+
+  #[test]
+  fn test_into_io_error() {
+    fn read(input: &str) -> std::io::Result<Json> {
+      Ok(Json::parse(input)?)
+    }
+
+    assert!(read("{}").is_ok());
+    let err = read("{").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(
+      err.to_string(),
+      "Unexpected end of input at line 1, column 1"
+    );
+    assert!(err.into_inner().unwrap().is::<crate::ParseError>());
+
+    fn read_file(path: &str) -> std::io::Result<Json> {
+      Ok(Json::parse_file(path)?)
+    }
+
+    let err = read_file("does/not/exist.json").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+  }
+}
@@ -0,0 +1,287 @@
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+use crate::{
+  error::ParseError,
+  span::{Loc, Span},
+};
+
+lazy_static! {
+  // `\x` 两字符转义 -> 对应的字符，\u 单独处理
+  static ref ESCAPES: HashMap<char, char> = HashMap::from([
+    ('"', '"'),
+    ('\\', '\\'),
+    ('/', '/'),
+    ('b', '\u{08}'),
+    ('f', '\u{0c}'),
+    ('n', '\n'),
+    ('r', '\r'),
+    ('t', '\t'),
+  ]);
+}
+
+// 转义成 JSON 字符串的内容，不包含两边的引号
+pub fn escape_string(s: &str) -> String {
+  let mut output = String::with_capacity(s.len());
+  escape_into(&mut output, s, false);
+  output
+}
+
+// 还原 JSON 字符串的内容，raw 不包含两边的引号，\' 不是合法的转义
+pub fn unescape_string(raw: &str) -> Result<String, ParseError> {
+  unescape_at(
+    raw,
+    Loc {
+      line: 1,
+      column: 1,
+      offset: 0,
+    },
+    false,
+  )
+}
+
+// ascii_only 时非 ASCII 字符也转义成 \u，超出 BMP 的写成代理对
+pub(crate) fn escape_into(output: &mut String, s: &str, ascii_only: bool) {
+  for c in s.chars() {
+    match c {
+      '"' => output.push_str("\\\""),
+      '\\' => output.push_str("\\\\"),
+      '\u{08}' => output.push_str("\\b"),
+      '\u{0c}' => output.push_str("\\f"),
+      '\n' => output.push_str("\\n"),
+      '\r' => output.push_str("\\r"),
+      '\t' => output.push_str("\\t"),
+      c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+      c if ascii_only && !c.is_ascii() => {
+        for unit in c.encode_utf16(&mut [0; 2]) {
+          output.push_str(&format!("\\u{:04x}", unit));
+        }
+      }
+      c => output.push(c),
+    }
+  }
+}
+
+// start 是 raw 第一个字符在源码中的位置，用于错误信息；
+// allow_single_quotes 时才接受 \'
+pub(crate) fn unescape_at(
+  raw: &str,
+  start: Loc,
+  allow_single_quotes: bool,
+) -> Result<String, ParseError> {
+  let mut ret = String::new();
+  let chars = raw.chars().collect::<Vec<char>>();
+  let mut index = 0;
+  let mut loc = start;
+
+  while let Some(c) = chars.get(index) {
+    let start_loc = loc.clone();
+    index += 1;
+    advance_loc(&mut loc, *c, chars.get(index));
+
+    match c {
+      '\\' => {
+        let Some(next_c) = chars.get(index) else {
+          return Err(ParseError::new(
+            "Unterminated escape sequence",
+            Span {
+              start: start_loc,
+              end: loc,
+            },
+          ));
+        };
+        index += 1;
+        advance_loc(&mut loc, *next_c, chars.get(index));
+
+        match next_c {
+          'u' => {
+            // 解析 unicode 字符，后面必须跟 4 位十六进制数字
+            let Some(hex) = chars.get(index..index + 4) else {
+              return Err(ParseError::new(
+                "Incomplete unicode escape",
+                Span {
+                  start: start_loc,
+                  end: loc,
+                },
+              ));
+            };
+            let hex = hex.iter().collect::<String>();
+            index += 4;
+            loc.column += 4;
+            loc.offset += 4;
+
+            let invalid = |hex: &str, end: &Loc| {
+              ParseError::new(
+                format!("Invalid unicode escape: \\u{}", hex),
+                Span {
+                  start: start_loc.clone(),
+                  end: end.clone(),
+                },
+              )
+            };
+            let unit = parse_hex(&hex).ok_or_else(|| invalid(&hex, &loc))?;
+
+            let unicode = match unit {
+              // 高位代理，后面必须紧跟 \uDC00-\uDFFF 的低位代理
+              0xD800..=0xDBFF => {
+                let low = chars
+                  .get(index..index + 6)
+                  .filter(|next| next[0] == '\\' && next[1] == 'u')
+                  .and_then(|next| parse_hex(&next[2..].iter().collect::<String>()))
+                  .filter(|low| (0xDC00..=0xDFFF).contains(low))
+                  .ok_or_else(|| invalid(&hex, &loc))?;
+                index += 6;
+                loc.column += 6;
+                loc.offset += 6;
+
+                char::from_u32(0x10000 + ((unit as u32 - 0xD800) << 10) + (low as u32 - 0xDC00))
+              }
+              _ => char::from_u32(unit as u32),
+            }
+            .ok_or_else(|| invalid(&hex, &loc))?;
+            ret.push(unicode);
+          }
+          '\'' if allow_single_quotes => ret.push('\''),
+          _ if ESCAPES.contains_key(next_c) => ret.push(ESCAPES[next_c]),
+          _ => {
+            return Err(ParseError::new(
+              format!("Unexpected escape character: {}", next_c),
+              Span {
+                start: start_loc,
+                end: loc,
+              },
+            ))
+          }
+        }
+      }
+      _ => ret.push(*c),
+    }
+  }
+
+  Ok(ret)
+}
+
+fn parse_hex(hex: &str) -> Option<u16> {
+  Some(hex)
+    .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()))
+    .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+}
+
+// 按字符推进位置，CRLF 只算一次换行
+fn advance_loc(loc: &mut Loc, c: char, next_c: Option<&char>) {
+  loc.offset += 1;
+
+  match c {
+    '\r' if next_c != Some(&'\n') => {
+      loc.line += 1;
+      loc.column = 1;
+    }
+    '\n' => {
+      loc.line += 1;
+      loc.column = 1;
+    }
+    _ => loc.column += 1,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_escape_string() {
+    assert_eq!(escape_string("plain"), "plain");
+    assert_eq!(escape_string("say \"hi\""), "say \\\"hi\\\"");
+    assert_eq!(escape_string("a\\b"), "a\\\\b");
+    assert_eq!(
+      escape_string("\u{08}\u{0c}\n\r\t\u{00}\u{1f}"),
+      "\\b\\f\\n\\r\\t\\u0000\\u001f"
+    );
+    // 非 ASCII 字符和 / 不转义
+    assert_eq!(escape_string("wörld 😀 /"), "wörld 😀 /");
+  }
+
+  #[test]
+  fn test_unescape_string() {
+    assert_eq!(unescape_string("plain").unwrap(), "plain");
+    assert_eq!(unescape_string("say \\\"hi\\\"").unwrap(), "say \"hi\"");
+    assert_eq!(unescape_string("a\\\\b\\/").unwrap(), "a\\b/");
+    assert_eq!(
+      unescape_string("\\b\\f\\n\\r\\t\\u0000\\u001F").unwrap(),
+      "\u{08}\u{0c}\n\r\t\u{00}\u{1f}"
+    );
+    assert_eq!(unescape_string("\\u00e9\\u4e2d").unwrap(), "é中");
+
+    for s in ["", "\"\\/\u{01}", "line\nbreak", "😀 \u{7f}"] {
+      assert_eq!(unescape_string(&escape_string(s)).unwrap(), s);
+    }
+  }
+
+  #[test]
+  fn test_surrogate_pairs() {
+    assert_eq!(unescape_string("\\uD83D\\uDE00").unwrap(), "😀");
+    assert_eq!(unescape_string("a\\ud834\\udd1eb").unwrap(), "a𝄞b");
+
+    // 单独的代理项不是合法字符
+    let err = unescape_string("\\uD83D").unwrap_err();
+    assert_eq!(err.message, "Invalid unicode escape: \\uD83D");
+    assert!(unescape_string("\\uD83Dx\\uDE00").is_err());
+    assert!(unescape_string("\\uD83D\\u0041").is_err());
+    assert!(unescape_string("\\uDE00").is_err());
+  }
+
+  #[test]
+  fn test_unescape_errors() {
+    let err = unescape_string("ab\\x").unwrap_err();
+    assert_eq!(err.message, "Unexpected escape character: x");
+    assert_eq!(err.span.unwrap().start.offset, 2);
+
+    assert!(unescape_string("\\").is_err());
+    assert!(unescape_string("\\u12").is_err());
+
+    // \' 只在允许单引号字符串时合法
+    let err = unescape_string("it\\'s").unwrap_err();
+    assert_eq!(err.message, "Unexpected escape character: '");
+    assert!(crate::Json::parse(r#""\'""#).is_err());
+
+    let options = crate::ParseOptions {
+      allow_single_quotes: true,
+      ..crate::ParseOptions::default()
+    };
+    let json = crate::Json::parse_with_options(r#"["\'", '\'']"#, &options).unwrap();
+    assert!(json.value_eq(&crate::json!(["'", "'"])));
+  }
+
+  #[test]
+  fn test_two_char_escapes() {
+    for (escape, expected) in [
+      ("\\\"", '"'),
+      ("\\\\", '\\'),
+      ("\\/", '/'),
+      ("\\b", '\u{08}'),
+      ("\\f", '\u{0c}'),
+      ("\\n", '\n'),
+      ("\\r", '\r'),
+      ("\\t", '\t'),
+    ] {
+      let json = crate::Json::parse(&format!("\"{}\"", escape)).unwrap();
+      assert_eq!(
+        json.as_str(),
+        Some(expected.to_string().as_str()),
+        "{}",
+        escape
+      );
+    }
+  }
+
+  #[test]
+  fn test_solidus_round_trip() {
+    let json = crate::Json::parse("\"a\\/b\\n\"").unwrap();
+    assert_eq!(json.as_str(), Some("a/b\n"));
+
+    // 输出时 / 不再转义，重新解析后的值不变
+    let output = json.to_string().unwrap();
+    assert_eq!(output, "\"a/b\\n\"");
+    assert!(crate::Json::parse(&output).unwrap().value_eq(&json));
+  }
+}
@@ -0,0 +1,304 @@
+use std::ops::ControlFlow;
+
+use crate::{
+  error::ParseError,
+  parser::{parse_string, unexpected_token},
+  span::Span,
+  tokenizer::{Token, Tokenizer},
+};
+
+// 事件回调，返回 ControlFlow::Break 可以提前终止解析。
+// *_start 收到的是 { 或 [ 的 span，*_end 收到的是整个对象或数组的 span
+pub trait Handler {
+  fn on_object_start(&mut self, _span: &Span) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
+
+  fn on_object_end(&mut self, _span: &Span) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
+
+  fn on_array_start(&mut self, _span: &Span) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
+
+  fn on_array_end(&mut self, _span: &Span) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
+
+  // key 已经解码
+  fn on_key(&mut self, _key: &str, _span: &Span) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
+
+  fn on_string(&mut self, _value: &str, _span: &Span) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
+
+  fn on_number(&mut self, _value: f64, _raw: &str, _span: &Span) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
+
+  fn on_boolean(&mut self, _value: bool, _span: &Span) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
+
+  fn on_null(&mut self, _span: &Span) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+  Value,
+  // [ 之后，值或者 ]
+  FirstValue,
+  // { 之后，key 或者 }
+  FirstKey,
+  Key,
+  Colon,
+  // 值之后，逗号或者结束符
+  AfterValue,
+  // 顶层的值已经结束
+  Done,
+}
+
+// 边切分 token 边分发事件，不构建 Ast，也不保存 token 列表。
+// 语法与 Json::parse 相同，但顶层的值之后不能再有其他内容；
+// handler 中断时返回 Ok(ControlFlow::Break(()))
+pub fn parse_events<H: Handler>(
+  input: &str,
+  handler: &mut H,
+) -> Result<ControlFlow<()>, ParseError> {
+  let mut tokenizer = Tokenizer::new(input);
+  // 未闭合的对象（true）或数组（false），以及开始符号的 span
+  let mut stack: Vec<(bool, Span)> = vec![];
+  let mut state = State::Value;
+  let mut last_span = None;
+
+  while let Some(token) = tokenizer.next_token()? {
+    let flow = match (state, &token) {
+      (State::FirstKey | State::AfterValue, Token::RightBrace(end))
+        if matches!(stack.last(), Some((true, _))) =>
+      {
+        let (_, start) = stack.pop().unwrap();
+        state = after_value(&stack);
+        handler.on_object_end(&start.merge(&end.span))
+      }
+      (State::FirstValue | State::AfterValue, Token::RightBracket(end))
+        if matches!(stack.last(), Some((false, _))) =>
+      {
+        let (_, start) = stack.pop().unwrap();
+        state = after_value(&stack);
+        handler.on_array_end(&start.merge(&end.span))
+      }
+      (State::AfterValue, Token::Comma(_)) => {
+        state = match stack.last() {
+          Some((true, _)) => State::Key,
+          _ => State::Value,
+        };
+        ControlFlow::Continue(())
+      }
+      (State::FirstKey | State::Key, Token::String(key)) => {
+        state = State::Colon;
+        handler.on_key(&parse_string(&key.value, &key.span, false)?, &key.span)
+      }
+      (State::FirstKey | State::Key, Token::Identifier(key)) => {
+        state = State::Colon;
+        handler.on_key(&key.value, &key.span)
+      }
+      (State::Colon, Token::Colon(_)) => {
+        state = State::Value;
+        ControlFlow::Continue(())
+      }
+      (State::Value | State::FirstValue, Token::LeftBrace(start)) => {
+        stack.push((true, start.span.clone()));
+        state = State::FirstKey;
+        handler.on_object_start(&start.span)
+      }
+      (State::Value | State::FirstValue, Token::LeftBracket(start)) => {
+        stack.push((false, start.span.clone()));
+        state = State::FirstValue;
+        handler.on_array_start(&start.span)
+      }
+      (State::Value | State::FirstValue, Token::String(value)) => {
+        state = after_value(&stack);
+        handler.on_string(
+          &parse_string(&value.value, &value.span, false)?,
+          &value.span,
+        )
+      }
+      (State::Value | State::FirstValue, Token::Number(value)) => {
+        state = after_value(&stack);
+        handler.on_number(value.value, &value.raw, &value.span)
+      }
+      (State::Value | State::FirstValue, Token::Boolean(value)) => {
+        state = after_value(&stack);
+        handler.on_boolean(value.value, &value.span)
+      }
+      (State::Value | State::FirstValue, Token::Null(value)) => {
+        state = after_value(&stack);
+        handler.on_null(&value.span)
+      }
+      _ => return Err(unexpected_token(&token)),
+    };
+
+    if flow.is_break() {
+      return Ok(flow);
+    }
+    last_span = Some(token.get_span().clone());
+  }
+
+  match (state, last_span) {
+    (State::Done, _) => Ok(ControlFlow::Continue(())),
+    (_, None) => Err(ParseError::empty_input()),
+    (_, last_span) => Err(ParseError::eof(last_span)),
+  }
+}
+
+fn after_value(stack: &[(bool, Span)]) -> State {
+  if stack.is_empty() {
+    State::Done
+  } else {
+    State::AfterValue
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Default)]
+  struct Recorder {
+    events: Vec<String>,
+    // 遇到这个字符串时中断
+    stop_at: Option<&'static str>,
+  }
+
+  impl Handler for Recorder {
+    fn on_object_start(&mut self, _span: &Span) -> ControlFlow<()> {
+      self.events.push("{".to_string());
+      ControlFlow::Continue(())
+    }
+
+    fn on_object_end(&mut self, span: &Span) -> ControlFlow<()> {
+      self
+        .events
+        .push(format!("}} {}..{}", span.start.offset, span.end.offset));
+      ControlFlow::Continue(())
+    }
+
+    fn on_array_start(&mut self, _span: &Span) -> ControlFlow<()> {
+      self.events.push("[".to_string());
+      ControlFlow::Continue(())
+    }
+
+    fn on_array_end(&mut self, span: &Span) -> ControlFlow<()> {
+      self
+        .events
+        .push(format!("] {}..{}", span.start.offset, span.end.offset));
+      ControlFlow::Continue(())
+    }
+
+    fn on_key(&mut self, key: &str, _span: &Span) -> ControlFlow<()> {
+      self.events.push(format!("key {}", key));
+      ControlFlow::Continue(())
+    }
+
+    fn on_string(&mut self, value: &str, _span: &Span) -> ControlFlow<()> {
+      self.events.push(format!("string {}", value));
+      if self.stop_at == Some(value) {
+        return ControlFlow::Break(());
+      }
+      ControlFlow::Continue(())
+    }
+
+    fn on_number(&mut self, _value: f64, raw: &str, _span: &Span) -> ControlFlow<()> {
+      self.events.push(format!("number {}", raw));
+      ControlFlow::Continue(())
+    }
+
+    fn on_boolean(&mut self, value: bool, _span: &Span) -> ControlFlow<()> {
+      self.events.push(format!("boolean {}", value));
+      ControlFlow::Continue(())
+    }
+
+    fn on_null(&mut self, _span: &Span) -> ControlFlow<()> {
+      self.events.push("null".to_string());
+      ControlFlow::Continue(())
+    }
+  }
+
+  #[test]
+  fn test_events() {
+    let mut recorder = Recorder::default();
+    let flow = parse_events(
+      "{\"a\": [1, \"x\\n\", {}], \"b\\u0021\": {\"c\": [true, null]}, \"d\": []}",
+      &mut recorder,
+    );
+
+    assert_eq!(flow, Ok(ControlFlow::Continue(())));
+    assert_eq!(
+      recorder.events,
+      [
+        "{",
+        "key a",
+        "[",
+        "number 1",
+        "string x\n",
+        "{",
+        "} 17..19",
+        "] 6..20",
+        "key b!",
+        "{",
+        "key c",
+        "[",
+        "boolean true",
+        "null",
+        "] 39..51",
+        "} 33..52",
+        "key d",
+        "[",
+        "] 59..61",
+        "} 0..62",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_events_break() {
+    let mut recorder = Recorder {
+      stop_at: Some("stop"),
+      ..Recorder::default()
+    };
+    // 中断之后的内容不再切分，即使后面有语法错误
+    let flow = parse_events("[[1, \"stop\", 2], 3, @", &mut recorder);
+
+    assert_eq!(flow, Ok(ControlFlow::Break(())));
+    assert_eq!(recorder.events, ["[", "[", "number 1", "string stop"]);
+  }
+
+  #[test]
+  fn test_events_errors() {
+    let mut recorder = Recorder::default();
+    for input in [
+      "",
+      "[1, 2",
+      "{\"a\" 1}",
+      "[1,]",
+      "{\"a\": }",
+      "[1 2]",
+      "{1: 2}",
+    ] {
+      assert_eq!(
+        parse_events(input, &mut recorder).unwrap_err(),
+        crate::Json::parse(input).unwrap_err(),
+        "{}",
+        input
+      );
+    }
+
+    let err = parse_events("{\"a\": 1}}", &mut recorder).unwrap_err();
+    assert_eq!(err.to_string(), "Unexpected '}' at line 1, column 9");
+  }
+}
@@ -0,0 +1,64 @@
+use crate::parser::{Ast, AstRef};
+
+impl Ast {
+  // 找到包含 offset 的最内层节点，key 返回对应的 Identifier
+  pub fn find_at(&self, offset: usize) -> Option<AstRef<'_>> {
+    find_at(AstRef::from(self), offset)
+  }
+}
+
+fn find_at(node: AstRef<'_>, offset: usize) -> Option<AstRef<'_>> {
+  if !node.get_span().contains(offset) {
+    return None;
+  }
+
+  let child = match node {
+    AstRef::Object(ast) => ast
+      .value
+      .iter()
+      .find_map(|property| find_at(AstRef::Property(property), offset)),
+    AstRef::Property(ast) => find_at(AstRef::Identifier(&ast.key), offset)
+      .or_else(|| find_at(AstRef::from(ast.value.as_ref()), offset)),
+    AstRef::Array(ast) => ast
+      .value
+      .iter()
+      .find_map(|item| find_at(AstRef::from(item.as_ref()), offset)),
+    _ => None,
+  };
+
+  child.or(Some(node))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::Json;
+
+  use super::*;
+
+  #[test]
+  fn test_find_at() {
+    // 0123456789012345678901234
+    // {"a": [1, true], "b": {}}
+    let json = Json::parse("{\"a\": [1, true], \"b\": {}}").unwrap();
+
+    assert!(matches!(json.find_at(1), Some(AstRef::Identifier(key)) if key.value.value == "a"));
+    assert!(matches!(json.find_at(3), Some(AstRef::Identifier(_))));
+    assert!(matches!(json.find_at(7), Some(AstRef::Number(n)) if n.value == 1.0));
+    assert!(matches!(json.find_at(10), Some(AstRef::Boolean(_))));
+    assert!(matches!(json.find_at(22), Some(AstRef::Object(o)) if o.value.is_empty()));
+    assert!(matches!(json.find_at(24), Some(AstRef::Object(o)) if o.value.len() == 2));
+  }
+
+  #[test]
+  fn test_find_at_gaps() {
+    let json = Json::parse("{\"a\": [1, true], \"b\": {}}").unwrap();
+
+    // 冒号属于 property
+    assert!(matches!(json.find_at(4), Some(AstRef::Property(p)) if p.key.value.value == "a"));
+    // 数组内的逗号属于数组
+    assert!(matches!(json.find_at(8), Some(AstRef::Array(_))));
+    // 属性之间的逗号属于对象
+    assert!(matches!(json.find_at(15), Some(AstRef::Object(_))));
+    assert_eq!(json.find_at(25), None);
+  }
+}
@@ -0,0 +1,103 @@
+use std::hash::{Hash, Hasher};
+
+use crate::parser::{eq_by, Ast, PropertyAst};
+
+// 只按值比较和哈希，忽略 span，可以作为 HashMap/HashSet 的 key。
+// 数字按 to_bits 处理：-0 与 0 相等，所有 NaN 彼此相等
+#[derive(Debug, Clone, Copy)]
+pub struct ValueHash<'a>(pub &'a Ast);
+
+impl<'a> PartialEq for ValueHash<'a> {
+  fn eq(&self, other: &Self) -> bool {
+    eq_by(self.0, other.0, |a, b| number_bits(a) == number_bits(b))
+  }
+}
+
+impl<'a> Eq for ValueHash<'a> {}
+
+impl<'a> Hash for ValueHash<'a> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    hash(self.0, state);
+  }
+}
+
+fn number_bits(value: f64) -> u64 {
+  if value.is_nan() {
+    f64::NAN.to_bits()
+  } else if value == 0.0 {
+    0
+  } else {
+    value.to_bits()
+  }
+}
+
+fn hash<H: Hasher>(ast: &Ast, state: &mut H) {
+  std::mem::discriminant(ast).hash(state);
+
+  match ast {
+    Ast::String(ast) => ast.value.hash(state),
+    Ast::Number(ast) => number_bits(ast.value).hash(state),
+    Ast::Boolean(ast) => ast.value.hash(state),
+    Ast::Null(_) => {}
+    Ast::Object(ast) => {
+      ast.value.len().hash(state);
+      for property in &ast.value {
+        property_hash(property, state);
+      }
+    }
+    Ast::Property(ast) => property_hash(ast, state),
+    Ast::Identifier(ast) => ast.value.value.hash(state),
+    Ast::Array(ast) => {
+      ast.value.len().hash(state);
+      for item in &ast.value {
+        hash(item, state);
+      }
+    }
+  }
+}
+
+fn property_hash<H: Hasher>(property: &PropertyAst, state: &mut H) {
+  property.key.value.value.hash(state);
+  hash(&property.value, state);
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+
+  use super::*;
+  use crate::Json;
+
+  #[test]
+  fn test_value_hash_set() {
+    let a = Json::parse("{\"a\": [1, 2.0, \"x\"], \"b\": null}").unwrap();
+    let b = Json::parse("{\n  \"a\": [1.0, 2, \"x\"],\n  \"b\": null\n}").unwrap();
+    let c = Json::parse("{\"b\": null, \"a\": [1, 2, \"x\"]}").unwrap();
+
+    let set = [&a, &b, &c]
+      .into_iter()
+      .map(ValueHash)
+      .collect::<HashSet<ValueHash>>();
+
+    // 对象 key 的顺序不同，视为不同的值，与 value_eq 一致
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(&ValueHash(&b)));
+  }
+
+  #[test]
+  fn test_value_hash_numbers() {
+    let items = Json::parse("[0, -0, 1e2, 100, \"100\", true, null]").unwrap();
+    let Json::Array(array) = &items else {
+      panic!("expected an array");
+    };
+    let set = array
+      .value
+      .iter()
+      .map(|item| ValueHash(item))
+      .collect::<HashSet<ValueHash>>();
+    assert_eq!(set.len(), 5);
+
+    let nan = Json::from(f64::NAN);
+    assert_eq!(ValueHash(&nan), ValueHash(&Json::from(-f64::NAN)));
+  }
+}
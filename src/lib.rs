@@ -1,32 +1,266 @@
-use std::str::FromStr;
+use std::{fs, path::Path, str::FromStr};
 
 use parser::{Ast, Parser};
 
-use crate::tokenizer::Tokenizer;
+use crate::{
+  span::{Loc, Span},
+  tokenizer::{classify_tokens, Token, TokenKind, Tokenizer},
+};
 
+pub use convert::ConvertError;
+pub use error::{ErrorWithSource, JsonError, ParseError, ParseErrorKind, SourceLines};
+pub use escape::{escape_string, unescape_string};
+pub use hash::ValueHash;
+pub use options::{DuplicateKeyPolicy, ParseOptions};
+#[cfg(feature = "serde_json")]
+pub use serde_bridge::ParseIntoError;
+pub use stats::Stats;
+
+mod convert;
+mod error;
+mod escape;
+pub mod events;
+mod find;
+mod hash;
+mod macros;
+pub mod merge;
+pub mod options;
 pub mod parser;
+pub mod patch;
+pub mod pointer;
+#[cfg(feature = "serde_json")]
+mod serde_bridge;
+pub mod serialize;
 pub mod span;
-mod tokenizer;
+mod stats;
+pub mod stream;
+pub mod tokenizer;
+mod validate;
 pub mod visit;
 
 pub type Json = Ast;
 
 impl FromStr for Json {
-  type Err = String;
+  type Err = ParseError;
   fn from_str(s: &str) -> Result<Self, Self::Err> {
     Json::parse(s)
   }
 }
 
 impl Json {
-  pub fn parse(input: &str) -> Result<Json, String> {
-    let tokens = Tokenizer::new(input).tokenize()?;
-    Parser::new(&tokens).parse()
+  pub fn parse(input: &str) -> Result<Json, ParseError> {
+    Json::parse_with_options(input, &ParseOptions::default())
+  }
+
+  pub fn parse_with_options(input: &str, options: &ParseOptions) -> Result<Json, ParseError> {
+    let mut tokens = vec![];
+    parse_into(input, options, &mut tokens)
+  }
+
+  // 类似 JSON5：注释、尾逗号、单引号、未加引号的 key、NaN/Infinity
+  pub fn parse_relaxed(input: &str) -> Result<Json, ParseError> {
+    Json::parse_with_options(
+      input,
+      &ParseOptions {
+        allow_single_quotes: true,
+        allow_unquoted_keys: true,
+        allow_non_finite: true,
+        allow_comments: true,
+        allow_trailing_commas: true,
+        ..ParseOptions::default()
+      },
+    )
+  }
+
+  // 只校验输入是否合法，不构建 Ast
+  pub fn validate(input: &str) -> Result<(), ParseError> {
+    Json::validate_with_options(input, &ParseOptions::default())
+  }
+
+  // 与 parse_with_options 接受的输入相同，报告的错误也相同，但不保存 token 列表
+  pub fn validate_with_options(input: &str, options: &ParseOptions) -> Result<(), ParseError> {
+    validate::validate(input, options)
+  }
+
+  pub fn parse_bytes(input: &[u8]) -> Result<Json, ParseError> {
+    match std::str::from_utf8(input) {
+      Ok(input) => Json::parse(input),
+      Err(e) => {
+        let byte_offset = e.valid_up_to();
+        // 合法前缀一定是完整的 UTF-8
+        let valid = std::str::from_utf8(&input[..byte_offset]).unwrap();
+        let loc = end_loc(valid);
+
+        Err(ParseError::invalid_utf8(
+          byte_offset,
+          Span {
+            start: loc.clone(),
+            end: loc,
+          },
+        ))
+      }
+    }
+  }
+
+  pub fn parse_file(path: impl AsRef<Path>) -> Result<Json, JsonError> {
+    let path = path.as_ref();
+    let input = fs::read_to_string(path)?;
+
+    Json::parse(&input).map_err(|error| JsonError::Parse {
+      path: path.to_path_buf(),
+      error,
+    })
+  }
+
+  // 收集所有语法错误而不是遇到第一个就返回，词法错误之后的内容会被忽略
+  pub fn parse_recovering(input: &str) -> (Option<Json>, Vec<ParseError>) {
+    Json::parse_recovering_with_options(input, &ParseOptions::default())
+  }
+
+  pub fn parse_recovering_with_options(
+    input: &str,
+    options: &ParseOptions,
+  ) -> (Option<Json>, Vec<ParseError>) {
+    let mut tokenizer = tokenizer(input, options);
+    if let Err(error) = tokenizer.check_total_len() {
+      return (None, vec![error]);
+    }
+
+    let mut tokens = vec![];
+    let mut lex_error = None;
+
+    loop {
+      match tokenizer.next_token() {
+        Ok(Some(token)) => tokens.push(token),
+        Ok(None) => break,
+        Err(error) => {
+          lex_error = Some(error);
+          break;
+        }
+      }
+    }
+
+    let (json, mut errors) = Parser::with_options(&tokens, options).parse_recovering();
+
+    if let Some(error) = lex_error {
+      // 词法错误导致 token 提前结束，由此产生的 EOF 错误没有意义
+      errors.retain(|error| {
+        !matches!(
+          error.kind,
+          ParseErrorKind::UnexpectedEof | ParseErrorKind::EmptyInput
+        )
+      });
+      errors.push(error);
+    }
+
+    (json, errors)
+  }
+
+  // 解析的同时返回每个 token 的 span 和分类，供语法高亮使用，开启 allow_comments 时包含注释
+  pub fn parse_and_collect_spans(
+    input: &str,
+    options: &ParseOptions,
+  ) -> Result<(Json, Vec<(Span, TokenKind)>), ParseError> {
+    let tokens = Tokenizer::with_trivia(input, options.clone()).tokenize()?;
+    let json = Parser::with_options(&tokens, options).parse()?;
+
+    Ok((json, classify_tokens(&tokens)))
+  }
+
+  pub fn parse_many(input: &str) -> Result<Vec<Json>, ParseError> {
+    Json::parse_many_with_options(input, &ParseOptions::default())
+  }
+
+  // require_top_level_composite 对每个值分别检查
+  pub fn parse_many_with_options(
+    input: &str,
+    options: &ParseOptions,
+  ) -> Result<Vec<Json>, ParseError> {
+    let tokens = tokenizer(input, options).tokenize()?;
+    Parser::with_options(&tokens, options).parse_many()
   }
 }
 
+// 反复解析大量小文档时复用内部的 token 缓冲区，避免每次重新分配
+#[derive(Default)]
+pub struct ReusableParser {
+  options: ParseOptions,
+  tokens: Vec<Token>,
+}
+
+impl ReusableParser {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_options(options: ParseOptions) -> Self {
+    Self {
+      options,
+      tokens: vec![],
+    }
+  }
+
+  // 与 Json::parse_with_options 结果相同
+  pub fn parse(&mut self, input: &str) -> Result<Json, ParseError> {
+    self.tokens.clear();
+    parse_into(input, &self.options, &mut self.tokens)
+  }
+}
+
+fn parse_into(
+  input: &str,
+  options: &ParseOptions,
+  tokens: &mut Vec<Token>,
+) -> Result<Json, ParseError> {
+  tokenizer(input, options).tokenize_into(tokens)?;
+  Parser::with_options(tokens, options).parse()
+}
+
+// attach_comments 需要 token 中保留注释
+fn tokenizer<'a>(input: &'a str, options: &ParseOptions) -> Tokenizer<'a> {
+  if options.attach_comments {
+    Tokenizer::with_trivia(input, options.clone())
+  } else {
+    Tokenizer::with_options(input, options.clone())
+  }
+}
+
+// 计算文本末尾的位置，换行规则与 Tokenizer 一致
+fn end_loc(text: &str) -> Loc {
+  let mut loc = Loc {
+    line: 1,
+    column: 1,
+    offset: 0,
+  };
+  let mut chars = text.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    loc.offset += 1;
+
+    match c {
+      '\r' => {
+        if chars.peek() == Some(&'\n') {
+          chars.next();
+          loc.offset += 1;
+        }
+        loc.line += 1;
+        loc.column = 1;
+      }
+      '\n' => {
+        loc.line += 1;
+        loc.column = 1;
+      }
+      _ => loc.column += 1,
+    }
+  }
+
+  loc
+}
+
 #[cfg(test)]
 mod tests {
+  use std::ops::ControlFlow;
+
   use crate::visit::Visit;
 
   use super::*;
@@ -47,6 +281,734 @@ mod tests {
     assert!(matches!(json, Json::Array(_)))
   }
 
+  #[test]
+  fn test_parse_many() {
+    let values = Json::parse_many("1 2 3").unwrap();
+    assert_eq!(values.len(), 3);
+    assert!(values.iter().all(|value| matches!(value, Json::Number(_))));
+
+    let values = Json::parse_many("{\"a\":1}\n{\"b\":2}").unwrap();
+    assert_eq!(values.len(), 2);
+    assert_eq!(values[1].get_span().start.line, 2);
+
+    assert_eq!(Json::parse_many("").unwrap(), vec![]);
+  }
+
+  #[test]
+  fn test_parse_recovering() {
+    let (json, errors) = Json::parse_recovering("{\"a\" 1, \"b\": [1 2, }], \"c\": 3}");
+    let json = json.unwrap();
+
+    let messages: Vec<_> = errors.iter().map(|error| error.to_string()).collect();
+    assert_eq!(
+      messages,
+      vec![
+        "Unexpected number at line 1, column 6",
+        "Unexpected number at line 1, column 17",
+        "Unexpected '}' at line 1, column 20",
+      ]
+    );
+
+    let object = json.as_object().unwrap();
+    assert!(!object.contains_key("a"));
+    assert_eq!(object.get("c").and_then(Json::as_f64), Some(3.0));
+
+    // 出错的元素用 null 占位，span 指向出错的 token
+    let array = object.get("b").unwrap().as_array().unwrap();
+    assert_eq!(array.value.len(), 2);
+    assert_eq!(array.value[0].as_f64(), Some(1.0));
+    assert!(array.value[1].is_null());
+    assert_eq!(array.value[1].get_span().start.column, 20);
+  }
+
+  #[test]
+  fn test_with_options_variants() {
+    let options = ParseOptions {
+      allow_comments: true,
+      allow_trailing_commas: true,
+      max_string_len: Some(3),
+      ..ParseOptions::default()
+    };
+
+    let values = Json::parse_many_with_options("[1,] // a\n{\"b\": 2,}", &options).unwrap();
+    assert_eq!(values.len(), 2);
+    assert!(Json::parse_many("[1,]").is_err());
+
+    let err = Json::parse_many_with_options("\"abcd\"", &options).unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::LimitExceeded);
+    let err = Json::parse_many_with_options(
+      "{} 1",
+      &ParseOptions {
+        require_top_level_composite: true,
+        ..ParseOptions::default()
+      },
+    )
+    .unwrap_err();
+    assert_eq!(err.span.unwrap().start.offset, 3);
+
+    let (json, errors) = Json::parse_recovering_with_options("[1, 2 3,] // x", &options);
+    assert!(json.unwrap().value_eq(&json!([1, 2])));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "Unexpected number");
+
+    let (json, errors) = Json::parse_recovering_with_options("[\"abcd\"]", &options);
+    assert_eq!(json, None);
+    assert_eq!(errors[0].kind, ParseErrorKind::LimitExceeded);
+
+    let (json, errors) = Json::parse_recovering_with_options(
+      "[1, 2]",
+      &ParseOptions {
+        max_total_len: Some(4),
+        ..ParseOptions::default()
+      },
+    );
+    assert_eq!(json, None);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, ParseErrorKind::LimitExceeded);
+  }
+
+  #[test]
+  fn test_parse_recovering_valid_and_fatal() {
+    let (json, errors) = Json::parse_recovering("[1, {\"a\": null}]");
+    assert_eq!(json, Some(Json::parse("[1, {\"a\": null}]").unwrap()));
+    assert!(errors.is_empty());
+
+    let (json, errors) = Json::parse_recovering("[1, 2");
+    assert_eq!(json, None);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, ParseErrorKind::UnexpectedEof);
+
+    let (json, errors) = Json::parse_recovering("[1 2, @]");
+    assert_eq!(json, None);
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].message, "Unexpected number");
+    assert_eq!(errors[1].span.as_ref().unwrap().start.column, 7);
+  }
+
+  #[test]
+  fn test_reusable_parser() {
+    let mut parser = ReusableParser::new();
+
+    for i in 0..3 {
+      let input = format!("{{\"id\": {}, \"tags\": [\"a\", \"b\"]}}", i);
+      assert_eq!(parser.parse(&input).unwrap(), Json::parse(&input).unwrap());
+    }
+
+    // 出错之后不影响下一次解析
+    assert_eq!(
+      parser.parse("[1, 2").unwrap_err(),
+      Json::parse("[1, 2").unwrap_err()
+    );
+    assert_eq!(parser.parse("true").unwrap(), Json::parse("true").unwrap());
+
+    let mut parser = ReusableParser::with_options(ParseOptions {
+      allow_comments: true,
+      require_top_level_composite: true,
+      ..ParseOptions::default()
+    });
+    assert!(parser.parse("[1] // x").is_ok());
+    assert!(parser.parse("1").is_err());
+  }
+
+  #[test]
+  fn test_parse_and_collect_spans() {
+    let (json, table) =
+      Json::parse_and_collect_spans("{\"a\":1}", &ParseOptions::default()).unwrap();
+    assert!(json.value_eq(&json!({"a": 1})));
+
+    let table = table
+      .into_iter()
+      .map(|(span, kind)| (span.start.offset, span.end.offset, kind))
+      .collect::<Vec<(usize, usize, TokenKind)>>();
+    assert_eq!(
+      table,
+      [
+        (0, 1, TokenKind::Punctuation),
+        (1, 4, TokenKind::Key),
+        (4, 5, TokenKind::Punctuation),
+        (5, 6, TokenKind::Number),
+        (6, 7, TokenKind::Punctuation),
+      ]
+    );
+
+    let options = ParseOptions {
+      allow_comments: true,
+      allow_unquoted_keys: true,
+      ..ParseOptions::default()
+    };
+    let (_, table) =
+      Json::parse_and_collect_spans("{k /* c */ : [\"v\", true, null]}", &options).unwrap();
+    let kinds = table
+      .into_iter()
+      .map(|(_, kind)| kind)
+      .collect::<Vec<TokenKind>>();
+    assert_eq!(
+      kinds,
+      [
+        TokenKind::Punctuation,
+        TokenKind::Key,
+        TokenKind::Comment,
+        TokenKind::Punctuation,
+        TokenKind::Punctuation,
+        TokenKind::String,
+        TokenKind::Punctuation,
+        TokenKind::Boolean,
+        TokenKind::Punctuation,
+        TokenKind::Null,
+        TokenKind::Punctuation,
+        TokenKind::Punctuation,
+      ]
+    );
+    assert!(Json::parse_and_collect_spans("{\"a\" 1}", &ParseOptions::default()).is_err());
+  }
+
+  #[test]
+  fn test_parse_many_error() {
+    let err = Json::parse_many("{\"a\":1}\n{\"b\" 2}").unwrap_err();
+    let span = err.span.unwrap();
+
+    assert_eq!((span.start.line, span.start.column), (2, 6));
+    assert_eq!(span.start.offset, 13);
+  }
+
+  #[test]
+  fn test_parse_file() {
+    let dir = std::env::temp_dir().join(format!("json_parser_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let valid = dir.join("valid.json");
+    fs::write(&valid, "{\"a\": [1, 2]}").unwrap();
+    assert!(Json::parse_file(&valid)
+      .unwrap()
+      .value_eq(&json!({"a": [1, 2]})));
+
+    let invalid = dir.join("invalid.json");
+    fs::write(&invalid, "{\"a\": [1, 2}").unwrap();
+    let err = Json::parse_file(&invalid).unwrap_err();
+    assert!(matches!(&err, JsonError::Parse { error, .. } if error.span.is_some()));
+    assert_eq!(
+      err.to_string(),
+      format!(
+        "{}: Unexpected '}}' at line 1, column 12",
+        invalid.display()
+      )
+    );
+
+    let err = Json::parse_file(dir.join("missing.json")).unwrap_err();
+    assert!(matches!(err, JsonError::Io(e) if e.kind() == std::io::ErrorKind::NotFound));
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_parse_bytes() {
+    let input = "{\"hello\": \"wörld\"}";
+    assert_eq!(
+      Json::parse_bytes(input.as_bytes()).unwrap(),
+      Json::parse(input).unwrap()
+    );
+
+    // "ö" 是两个字节，这里截掉了第二个字节
+    let err = Json::parse_bytes(b"[\"w\xc3\"]").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::InvalidUtf8 { byte_offset: 3 });
+    assert_eq!(err.span.unwrap().start.column, 4);
+
+    let err = Json::parse_bytes(b"[1,\n\xe4\xb8").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::InvalidUtf8 { byte_offset: 4 });
+    assert_eq!(err.span.unwrap().start.line, 2);
+  }
+
+  #[test]
+  fn test_single_quotes() {
+    let options = ParseOptions {
+      allow_single_quotes: true,
+      ..ParseOptions::default()
+    };
+    let json = Json::parse_with_options("{'a': 'it\\'s', \"b\": ['x', \"y\"]}", &options).unwrap();
+
+    assert!(json.value_eq(&json!({"a": "it's", "b": ["x", "y"]})));
+    assert!(Json::parse("{'a': 1}").is_err());
+  }
+
+  #[test]
+  fn test_unquoted_keys() {
+    let options = ParseOptions {
+      allow_unquoted_keys: true,
+      ..ParseOptions::default()
+    };
+    let json = Json::parse_with_options("{ key: 1, _b$: { c2: [true] } }", &options).unwrap();
+    assert!(json.value_eq(&json!({"key": 1, "_b$": {"c2": [true]}})));
+
+    // 只对 key 生效，值仍然必须是合法的字面量
+    assert!(Json::parse_with_options("{ key: value }", &options).is_err());
+    assert!(Json::parse_with_options("[abc]", &options).is_err());
+    assert!(Json::parse_with_options("{ a-b: 1 }", &options).is_err());
+    assert!(Json::parse_with_options("{ 1a: 1 }", &options).is_err());
+  }
+
+  #[test]
+  fn test_bare_strings() {
+    let options = ParseOptions {
+      allow_bare_strings: true,
+      ..ParseOptions::default()
+    };
+    let json = Json::parse_with_options("[ok, maybe]", &options).unwrap();
+    assert!(json.value_eq(&json!(["ok", "maybe"])));
+    assert_eq!(json.pointer("/1").unwrap().get_span().start.offset, 5);
+
+    // 关键字和数字不受影响
+    let json =
+      Json::parse_with_options("{status: ok, done: true, n: null, x: -1.5}", &options).unwrap();
+    assert!(json.value_eq(&json!({"status": "ok", "done": true, "n": null, "x": -1.5})));
+    assert!(Json::parse_with_options("[true]", &options)
+      .unwrap()
+      .value_eq(&json!([true])));
+    assert!(Json::parse_with_options("[NaN]", &options)
+      .unwrap()
+      .value_eq(&json!(["NaN"])));
+    assert!(Json::parse_with_options("[a-b]", &options).is_err());
+
+    // 默认关闭
+    assert!(Json::parse("[ok]").is_err());
+  }
+
+  #[test]
+  fn test_non_finite() {
+    let options = ParseOptions {
+      allow_non_finite: true,
+      ..ParseOptions::default()
+    };
+    let json = Json::parse_with_options("[NaN, Infinity, -Infinity, -1]", &options).unwrap();
+
+    let Json::Array(array) = &json else {
+      panic!("expected an array");
+    };
+    let values = array
+      .value
+      .iter()
+      .map(|item| match item.as_ref() {
+        Json::Number(n) => n.value,
+        _ => panic!("expected a number"),
+      })
+      .collect::<Vec<f64>>();
+    assert!(values[0].is_nan());
+    assert_eq!(values[1..], [f64::INFINITY, f64::NEG_INFINITY, -1.0]);
+
+    assert!(Json::parse("NaN").is_err());
+    assert!(Json::parse("[Infinity]").is_err());
+    assert!(Json::parse("-Infinity").is_err());
+  }
+
+  #[test]
+  fn test_comments() {
+    let options = ParseOptions {
+      allow_comments: true,
+      ..ParseOptions::default()
+    };
+    let json = Json::parse_with_options(
+      "// config\n{\n  \"a\": 1, /* inline */ \"b\": [2] // tail\n}",
+      &options,
+    )
+    .unwrap();
+    assert!(json.value_eq(&json!({"a": 1, "b": [2]})));
+
+    assert!(Json::parse("// x\n1").is_err());
+
+    // Parser 忽略 trivia token
+    let input = "/* x */ [1, // y\n 2]";
+    let tokens = Tokenizer::with_trivia(input, options.clone())
+      .tokenize()
+      .unwrap();
+    assert_eq!(
+      Parser::new(&tokens).parse().unwrap(),
+      Json::parse_with_options(input, &options).unwrap()
+    );
+    assert_eq!(
+      Parser::new(&tokens[..7]).parse().unwrap_err(),
+      Json::parse_with_options("/* x */ [1, // y", &options).unwrap_err()
+    );
+  }
+
+  #[test]
+  fn test_trailing_commas() {
+    let options = ParseOptions {
+      allow_trailing_commas: true,
+      ..ParseOptions::default()
+    };
+    let json = Json::parse_with_options("{\"a\": [1, 2,], \"b\": {},}", &options).unwrap();
+    assert!(json.value_eq(&json!({"a": [1, 2], "b": {}})));
+    assert_eq!(json.get_span().end.offset, 24);
+
+    assert!(Json::parse("[1,]").is_err());
+    assert!(Json::parse("{\"a\": 1,}").is_err());
+    assert!(Json::parse_with_options("[,]", &options).is_err());
+    assert!(Json::parse_with_options("[1,,]", &options).is_err());
+    assert!(Json::parse_with_options("{,}", &options).is_err());
+
+    assert!(Json::validate_with_options("[1, {\"a\": 2,},]", &options).is_ok());
+    assert!(Json::validate("[1, {\"a\": 2,},]").is_err());
+  }
+
+  #[test]
+  fn test_parse_relaxed() {
+    let json = Json::parse_relaxed(
+      r#"// 服务配置
+{
+  name: 'api',
+  port: 8080,
+  /* 上游 */
+  upstreams: [
+    'http://a.local',
+    "http://b.local",
+  ],
+  retry: { max: 3, backoff: Infinity, },
+  ratio: NaN,
+}
+"#,
+    )
+    .unwrap();
+
+    let object = json.as_object().unwrap();
+    assert_eq!(object.get("name").unwrap().as_str(), Some("api"));
+    assert_eq!(object.get("port").unwrap().as_f64(), Some(8080.0));
+    assert_eq!(
+      object
+        .get("upstreams")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .value
+        .len(),
+      2
+    );
+    assert!(object.get("ratio").unwrap().as_f64().unwrap().is_nan());
+
+    let retry = object.get("retry").unwrap().as_object().unwrap();
+    assert_eq!(retry.get("backoff").unwrap().as_f64(), Some(f64::INFINITY));
+
+    assert!(Json::parse_relaxed("{a: b}").is_err());
+  }
+
+  #[test]
+  fn test_require_top_level_composite() {
+    for input in ["\"just a string\"", "42", "true", "null"] {
+      assert!(Json::parse(input).is_ok());
+    }
+
+    let options = ParseOptions {
+      require_top_level_composite: true,
+      ..ParseOptions::default()
+    };
+    assert!(Json::parse_with_options("{\"a\": 1}", &options).is_ok());
+    assert!(Json::parse_with_options("[42]", &options).is_ok());
+
+    let err = Json::parse_with_options("  \"just a string\"", &options).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "Expected an object or array at the top level at line 1, column 3"
+    );
+    assert!(Json::parse_with_options("42", &options).is_err());
+    assert!(Json::parse_with_options("null", &options).is_err());
+  }
+
+  #[test]
+  fn test_duplicate_keys() {
+    let input = "{\"a\": 1, \"b\": 2, \"a\": 3, \"c\": {\"x\": 1, \"x\": 2}}";
+    let parse = |duplicate_keys| {
+      Json::parse_with_options(
+        input,
+        &ParseOptions {
+          duplicate_keys,
+          ..ParseOptions::default()
+        },
+      )
+    };
+
+    assert_eq!(
+      parse(None).unwrap().to_string().unwrap(),
+      "{\"a\":1,\"b\":2,\"a\":3,\"c\":{\"x\":1,\"x\":2}}"
+    );
+    assert_eq!(
+      parse(Some(DuplicateKeyPolicy::FirstWins))
+        .unwrap()
+        .to_string()
+        .unwrap(),
+      "{\"a\":1,\"b\":2,\"c\":{\"x\":1}}"
+    );
+    assert_eq!(
+      parse(Some(DuplicateKeyPolicy::LastWins))
+        .unwrap()
+        .to_string()
+        .unwrap(),
+      "{\"b\":2,\"a\":3,\"c\":{\"x\":2}}"
+    );
+
+    let err = parse(Some(DuplicateKeyPolicy::Error)).unwrap_err();
+    assert_eq!(err.to_string(), "Duplicate key \"a\" at line 1, column 18");
+    assert!(Json::parse_with_options(
+      "{\"a\": {\"a\": 1}}",
+      &ParseOptions {
+        duplicate_keys: Some(DuplicateKeyPolicy::Error),
+        ..ParseOptions::default()
+      }
+    )
+    .is_ok());
+  }
+
+  #[test]
+  fn test_duplicate_keys_compare_decoded() {
+    let parse = |input: &str, duplicate_keys| {
+      Json::parse_with_options(
+        input,
+        &ParseOptions {
+          duplicate_keys: Some(duplicate_keys),
+          allow_unquoted_keys: true,
+          ..ParseOptions::default()
+        },
+      )
+    };
+
+    // 比较的是解码后的 key，而不是原始写法
+    let input =
+      "{\"A\": 1, \"\\u0041\": 2, \"a\\/b\": 3, \"a/b\": 4, \"\\ud83d\\ude00\": 5, \"😀\": 6}";
+    let err = parse(input, DuplicateKeyPolicy::Error).unwrap_err();
+    assert_eq!(err.to_string(), "Duplicate key \"A\" at line 1, column 10");
+
+    let json = parse(input, DuplicateKeyPolicy::FirstWins).unwrap();
+    assert_eq!(json.to_string().unwrap(), "{\"A\":1,\"a/b\":3,\"😀\":5}");
+
+    let json = parse(input, DuplicateKeyPolicy::LastWins).unwrap();
+    assert_eq!(json.to_string().unwrap(), "{\"A\":2,\"a/b\":4,\"😀\":6}");
+
+    // 未加引号的 key 与等价的字符串 key 也算重复
+    let json = parse("{A: 1, \"\\u0041\": 2}", DuplicateKeyPolicy::LastWins).unwrap();
+    assert_eq!(json.to_string().unwrap(), "{\"A\":2}");
+
+    // 大小写不同不算重复
+    assert!(parse("{\"a\": 1, \"\\u0041\": 2}", DuplicateKeyPolicy::Error).is_ok());
+  }
+
+  #[test]
+  fn test_attach_comments() {
+    let options = ParseOptions {
+      allow_comments: true,
+      attach_comments: true,
+      ..ParseOptions::default()
+    };
+    let json = Json::parse_with_options(
+      r#"{ // header
+  // doc
+  /* more doc */
+  "a": 1, // trailing a
+  "b": [2,
+    3], /* b */ // end
+  // c doc
+  "c": {
+    // nested
+    "d": null
+  }
+  // dangling
+}"#,
+      &options,
+    )
+    .unwrap();
+
+    let object = json.as_object().unwrap();
+    let comments = |index: usize| {
+      let property = &object.value[index];
+      (
+        property.leading_comments.clone(),
+        property.trailing_comments.clone(),
+      )
+    };
+
+    assert_eq!(
+      comments(0),
+      (
+        vec![
+          "header".to_string(),
+          "doc".to_string(),
+          "more doc".to_string()
+        ],
+        vec!["trailing a".to_string()]
+      )
+    );
+    assert_eq!(
+      comments(1),
+      (vec![], vec!["b".to_string(), "end".to_string()])
+    );
+    assert_eq!(comments(2), (vec!["c doc".to_string()], vec![]));
+
+    let nested = object.get("c").unwrap().as_object().unwrap();
+    assert_eq!(nested.value[0].leading_comments, ["nested"]);
+
+    // 不开启时不挂注释
+    let json = Json::parse_with_options(
+      "{\n  // doc\n  \"a\": 1\n}",
+      &ParseOptions {
+        allow_comments: true,
+        ..ParseOptions::default()
+      },
+    )
+    .unwrap();
+    assert!(json.as_object().unwrap().value[0]
+      .leading_comments
+      .is_empty());
+  }
+
+  #[test]
+  fn test_validate() {
+    for input in [
+      "",
+      "  ",
+      "{\"a\": [1, true, null, {\"b\": \"c\"}]}",
+      "[]",
+      "{}",
+      "\"\\uD800\"",
+      "{\"a\" 1}",
+      "{\"a\": 1,}",
+      "[1, 2",
+      "[1 2]",
+      "{1: 2}",
+      "{\"a\":",
+      ":",
+      "[\"a\", @]",
+    ] {
+      assert_eq!(
+        Json::validate(input),
+        Json::parse(input).map(|_| ()),
+        "input: {:?}",
+        input
+      );
+    }
+  }
+
+  #[test]
+  fn test_validate_with_options() {
+    let relaxed = ParseOptions {
+      allow_single_quotes: true,
+      allow_unquoted_keys: true,
+      allow_non_finite: true,
+      allow_comments: true,
+      allow_trailing_commas: true,
+      ..ParseOptions::default()
+    };
+    let strict = ParseOptions {
+      duplicate_keys: Some(DuplicateKeyPolicy::Error),
+      max_array_entries: Some(2),
+      require_top_level_composite: true,
+      ..ParseOptions::default()
+    };
+
+    for (input, options) in [
+      ("// c\n{a: 'b', c: [NaN, Infinity,],}", &relaxed),
+      ("{a: b}", &relaxed),
+      ("[1, /* x */ 2", &relaxed),
+      ("{\"a\": 1, \"a\": 2}", &strict),
+      ("[[1, 2], [1, 2, 3]]", &strict),
+      ("[[1, 2], {\"a\": [3]}]", &strict),
+      ("\"top\"", &strict),
+    ] {
+      assert_eq!(
+        Json::validate_with_options(input, options),
+        Json::parse_with_options(input, options).map(|_| ()),
+        "input: {:?}",
+        input
+      );
+    }
+    assert!(Json::validate_with_options("{a: 'b'}", &relaxed).is_ok());
+    assert!(Json::validate("{a: 'b'}").is_err());
+  }
+
+  #[test]
+  fn test_eof_location() {
+    let err = Json::parse("{\"a\":").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::UnexpectedEof);
+    let span = err.span.unwrap();
+    assert_eq!((span.start.line, span.start.column), (1, 5));
+
+    let err = Json::parse("[1,\n").unwrap_err();
+    let span = err.span.unwrap();
+    assert_eq!((span.start.line, span.start.column), (1, 3));
+
+    let err = Json::parse("{\"a\": [1, {}").unwrap_err();
+    assert_eq!(err.span.as_ref().unwrap().start.offset, 11);
+    assert_eq!(err, Json::validate("{\"a\": [1, {}").unwrap_err());
+
+    assert_eq!(Json::parse("").unwrap_err().span, None);
+  }
+
+  #[test]
+  fn test_entry_limits() {
+    let options = ParseOptions {
+      max_object_entries: Some(2),
+      max_array_entries: Some(3),
+      ..ParseOptions::default()
+    };
+
+    let input = "{\"a\": [1, 2, 3], \"b\": {\"c\": [], \"d\": {}}}";
+    assert!(Json::parse_with_options(input, &options).is_ok());
+
+    let err = Json::parse_with_options("[1, 2, 3, [4]]", &options).unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::LimitExceeded);
+    assert_eq!(err.message, "Array exceeds the maximum of 3 entries");
+    let span = err.span.unwrap();
+    assert_eq!((span.start.offset, span.end.offset), (10, 13));
+
+    let err = Json::parse_with_options("[{\"a\": 1, \"b\": 2, \"c\": 3}]", &options).unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::LimitExceeded);
+    assert_eq!(err.message, "Object exceeds the maximum of 2 entries");
+    let span = err.span.unwrap();
+    assert_eq!((span.start.offset, span.end.offset), (18, 24));
+
+    // 被覆盖的重复 key 不计入个数
+    let options = ParseOptions {
+      duplicate_keys: Some(DuplicateKeyPolicy::LastWins),
+      ..options
+    };
+    assert!(Json::parse_with_options("{\"a\": 1, \"b\": 2, \"a\": 3}", &options).is_ok());
+  }
+
+  #[test]
+  fn test_empty_input() {
+    let options = ParseOptions {
+      allow_comments: true,
+      ..ParseOptions::default()
+    };
+
+    for input in ["", "  \n\t", "\u{FEFF}"] {
+      let err = Json::parse(input).unwrap_err();
+      assert_eq!(err.kind, ParseErrorKind::EmptyInput);
+      assert_eq!(err.to_string(), "Empty input");
+      assert_eq!(Json::validate(input).unwrap_err(), err);
+    }
+    let err = Json::parse_with_options(" // nothing\n", &options).unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::EmptyInput);
+
+    // 不完整的文档仍然是 UnexpectedEof
+    assert_eq!(
+      Json::parse("[").unwrap_err().kind,
+      ParseErrorKind::UnexpectedEof
+    );
+    // 词法错误时不再额外报告空输入
+    let (_, errors) = Json::parse_recovering("@");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, ParseErrorKind::Syntax);
+  }
+
+  #[test]
+  fn test_unexpected_token_message() {
+    let err = Json::parse("{\n  \"a\": 1,\n    ,\n}").unwrap_err();
+    assert_eq!(err.message, "Unexpected ','");
+    assert_eq!(err.to_string(), "Unexpected ',' at line 3, column 5");
+
+    let err = Json::parse("{\"a\" \"b\"}").unwrap_err();
+    assert_eq!(err.to_string(), "Unexpected string at line 1, column 6");
+
+    let err = Json::parse("[1 true]").unwrap_err();
+    assert_eq!(err.to_string(), "Unexpected boolean at line 1, column 4");
+  }
+
   #[test]
   fn test_visit() {
     let mut json = "{\"hello\":\"world\"}".parse::<Json>().unwrap();
@@ -57,19 +1019,21 @@ mod tests {
     }
 
     impl Visit for Visitor {
-      fn visit_property(&mut self, ast: &mut parser::PropertyAst) {
+      fn visit_property(&mut self, ast: &mut parser::PropertyAst) -> ControlFlow<()> {
         self.property_pos = (ast.span.start.offset, ast.span.end.offset);
 
-        self.visit_identifier(&mut ast.key);
-        self.visit_property_value(&mut ast.value);
+        self.visit_identifier(&mut ast.key)?;
+        self.visit_property_value(&ast.key.value.value, &mut ast.value)
       }
 
-      fn visit_string(&mut self, ast: &mut parser::StringAst) {
+      fn visit_string(&mut self, ast: &mut parser::StringAst) -> ControlFlow<()> {
         if self.merged_string.is_empty() {
           self.merged_string.push_str(&ast.value);
         } else {
           self.merged_string.push_str(&format!("_{}", ast.value));
         }
+
+        ControlFlow::Continue(())
       }
     }
 
@@ -78,7 +1042,7 @@ mod tests {
       merged_string: String::new(),
     };
 
-    visitor.visit_json(&mut json);
+    let _ = visitor.visit_json(&mut json);
     assert_eq!(visitor.property_pos, (1, 16));
     assert_eq!(visitor.merged_string, "hello_world");
   }
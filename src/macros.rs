@@ -0,0 +1,112 @@
+#[macro_export]
+macro_rules! json {
+  (null) => {
+    $crate::Json::from(())
+  };
+  ([$($tt:tt)*]) => {
+    $crate::json_internal!(@array [] $($tt)*)
+  };
+  ({$($tt:tt)*}) => {
+    $crate::json_internal!(@object [] $($tt)*)
+  };
+  ($other:expr) => {
+    $crate::Json::from($other)
+  };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! json_internal {
+  // 数组：逐个吃掉元素，null、数组、对象不是合法的表达式，需要单独匹配
+  (@array [$($elems:expr,)*]) => {
+    $crate::Json::from(vec![$($elems,)*])
+  };
+  (@array [$($elems:expr,)*] null $(, $($rest:tt)*)?) => {
+    $crate::json_internal!(@array [$($elems,)* $crate::json!(null),] $($($rest)*)?)
+  };
+  (@array [$($elems:expr,)*] [$($array:tt)*] $(, $($rest:tt)*)?) => {
+    $crate::json_internal!(@array [$($elems,)* $crate::json!([$($array)*]),] $($($rest)*)?)
+  };
+  (@array [$($elems:expr,)*] {$($object:tt)*} $(, $($rest:tt)*)?) => {
+    $crate::json_internal!(@array [$($elems,)* $crate::json!({$($object)*}),] $($($rest)*)?)
+  };
+  (@array [$($elems:expr,)*] $next:expr $(, $($rest:tt)*)?) => {
+    $crate::json_internal!(@array [$($elems,)* $crate::json!($next),] $($($rest)*)?)
+  };
+
+  // 对象：key 可以是字符串字面量，也可以是括号包裹的表达式
+  (@object [$($props:expr,)*]) => {
+    <$crate::Json as ::std::iter::FromIterator<(::std::string::String, $crate::Json)>>::from_iter(
+      vec![$($props,)*],
+    )
+  };
+  (@object [$($props:expr,)*] $key:tt : null $(, $($rest:tt)*)?) => {
+    $crate::json_internal!(
+      @object [$($props,)* (::std::string::String::from($key), $crate::json!(null)),]
+      $($($rest)*)?
+    )
+  };
+  (@object [$($props:expr,)*] $key:tt : [$($array:tt)*] $(, $($rest:tt)*)?) => {
+    $crate::json_internal!(
+      @object [$($props,)* (::std::string::String::from($key), $crate::json!([$($array)*])),]
+      $($($rest)*)?
+    )
+  };
+  (@object [$($props:expr,)*] $key:tt : {$($object:tt)*} $(, $($rest:tt)*)?) => {
+    $crate::json_internal!(
+      @object [$($props,)* (::std::string::String::from($key), $crate::json!({$($object)*})),]
+      $($($rest)*)?
+    )
+  };
+  (@object [$($props:expr,)*] $key:tt : $value:expr $(, $($rest:tt)*)?) => {
+    $crate::json_internal!(
+      @object [$($props,)* (::std::string::String::from($key), $crate::json!($value)),]
+      $($($rest)*)?
+    )
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::Json;
+
+  #[test]
+  fn test_json_macro() {
+    let parsed = Json::parse("{\"a\": [1, true, null], \"b\": {\"c\": \"d\", \"e\": []}}").unwrap();
+    let built = json!({
+      "a": [1, true, null],
+      "b": { "c": "d", "e": [] },
+    });
+
+    assert!(built.value_eq(&parsed));
+    assert_eq!(built.to_string().unwrap(), parsed.to_string().unwrap());
+  }
+
+  #[test]
+  fn test_json_macro_interpolation() {
+    let name = "world";
+    let key = String::from("k");
+    let inner = json!([1.5, -2]);
+    let built = json!({
+      "hello": name,
+      (key): inner,
+      "sum": 1 + 2,
+      "list": [null, [], {}, false,],
+    });
+
+    assert!(built.value_eq(
+      &Json::parse(
+        "{\"hello\": \"world\", \"k\": [1.5, -2], \"sum\": 3, \"list\": [null, [], {}, false]}"
+      )
+      .unwrap()
+    ));
+  }
+
+  #[test]
+  fn test_json_macro_scalars() {
+    assert!(json!(null).value_eq(&Json::parse("null").unwrap()));
+    assert!(json!("x").value_eq(&Json::parse("\"x\"").unwrap()));
+    assert!(json!([]).value_eq(&Json::parse("[]").unwrap()));
+    assert!(!json!([1]).value_eq(&json!([2])));
+  }
+}
@@ -0,0 +1,98 @@
+use std::mem;
+
+use crate::parser::Ast;
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum MergeStrategy {
+  // overlay 中的数组整体替换 base 中的数组
+  #[default]
+  Replace,
+  // 把 overlay 中的数组元素追加到 base 的数组后面
+  Concat,
+}
+
+impl Ast {
+  pub fn merge(base: Ast, overlay: Ast) -> Ast {
+    Ast::merge_with(base, overlay, MergeStrategy::default())
+  }
+
+  // 对象递归合并，overlay 的 key 优先；其他类型直接取 overlay
+  pub fn merge_with(base: Ast, overlay: Ast, strategy: MergeStrategy) -> Ast {
+    match (base, overlay) {
+      (Ast::Object(mut base), Ast::Object(overlay)) => {
+        for property in overlay.value {
+          let existing = base
+            .value
+            .iter_mut()
+            .find(|p| p.key.value.value == property.key.value.value);
+
+          match existing {
+            Some(existing) => {
+              let value = mem::replace(existing.value.as_mut(), Ast::from(()));
+              *existing.value = Ast::merge_with(value, *property.value, strategy);
+            }
+            None => base.value.push(property),
+          }
+        }
+
+        Ast::Object(base)
+      }
+      (Ast::Array(mut base), Ast::Array(overlay)) if strategy == MergeStrategy::Concat => {
+        base.value.extend(overlay.value);
+        Ast::Array(base)
+      }
+      (_, overlay) => overlay,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::json;
+
+  use super::*;
+
+  #[test]
+  fn test_merge_nested() {
+    let base = json!({
+      "name": "app",
+      "db": {"host": "localhost", "port": 5432},
+      "tags": ["a"],
+    });
+    let overlay = json!({
+      "db": {"port": 6543, "user": "root"},
+      "debug": true,
+    });
+
+    assert!(Ast::merge(base, overlay).value_eq(&json!({
+      "name": "app",
+      "db": {"host": "localhost", "port": 6543, "user": "root"},
+      "tags": ["a"],
+      "debug": true,
+    })));
+  }
+
+  #[test]
+  fn test_merge_override() {
+    // 类型不同时直接覆盖
+    let merged = Ast::merge(json!({"a": {"b": 1}}), json!({"a": "x"}));
+    assert!(merged.value_eq(&json!({"a": "x"})));
+
+    let merged = Ast::merge(json!({"a": 1}), json!({"a": {"b": 1}}));
+    assert!(merged.value_eq(&json!({"a": {"b": 1}})));
+
+    assert!(Ast::merge(json!({"a": 1}), json!(null)).value_eq(&json!(null)));
+  }
+
+  #[test]
+  fn test_merge_array_strategies() {
+    let base = || json!({"list": [1, 2], "nested": {"list": ["x"]}});
+    let overlay = || json!({"list": [3], "nested": {"list": ["y"]}});
+
+    assert!(
+      Ast::merge(base(), overlay()).value_eq(&json!({"list": [3], "nested": {"list": ["y"]}}))
+    );
+    assert!(Ast::merge_with(base(), overlay(), MergeStrategy::Concat)
+      .value_eq(&json!({"list": [1, 2, 3], "nested": {"list": ["x", "y"]}})));
+  }
+}
@@ -0,0 +1,77 @@
+// max_depth 的默认值，与 serde_json 相同
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseOptions {
+  // 允许 'single' 单引号字符串
+  pub allow_single_quotes: bool,
+  // 允许 { key: 1 } 这样未加引号的 key，只对 key 生效
+  pub allow_unquoted_keys: bool,
+  // 非常宽松：把值的位置上未加引号的单词当作字符串，如 {status: ok}，同时也允许未加引号的 key。
+  // true、false、null 仍是关键字，数字仍按数字解析；未开启 allow_non_finite 时 NaN、Infinity 会变成字符串。
+  // 拼错的关键字（如 nul）也会被静默当作字符串，默认关闭
+  pub allow_bare_strings: bool,
+  // 允许 NaN、Infinity、-Infinity
+  pub allow_non_finite: bool,
+  // 允许 // 和 /* */ 注释
+  pub allow_comments: bool,
+  // 允许数组、对象最后一个元素后面的逗号
+  pub allow_trailing_commas: bool,
+  // 允许字符串中出现未转义的 U+0000 到 U+001F，如直接换行
+  pub allow_control_chars: bool,
+  // 数字超出 f64 范围（如 1e400）时报错，否则静默变成 inf
+  pub reject_number_overflow: bool,
+  // 空白中的 \t 把列号推进到下一个制表位，如 4 时第 1 列的 tab 之后是第 5 列；
+  // 0 表示 tab 和其他字符一样只占一列。offset 始终按字符计算
+  pub tab_width: usize,
+  // 单个字符串（不含引号）的最大字节数
+  pub max_string_len: Option<usize>,
+  // 整个输入的最大字节数
+  pub max_total_len: Option<usize>,
+  // 单个对象的属性个数、单个数组的元素个数上限，超出时 span 指向多出来的那个元素
+  pub max_object_entries: Option<usize>,
+  pub max_array_entries: Option<usize>,
+  // 同时打开的 { 和 [ 的最大个数，默认 DEFAULT_MAX_DEPTH，超出时 span 指向多出来的那个 { 或 [。
+  // 设为 None 时解析和校验不受限制，但过深的 Ast 在 drop、序列化等递归操作中可能爆栈
+  pub max_depth: Option<usize>,
+  // 把注释挂到 PropertyAst 的 leading_comments、trailing_comments 上，需要同时开启 allow_comments
+  pub attach_comments: bool,
+  // 按 RFC 4627 要求顶层必须是对象或数组
+  pub require_top_level_composite: bool,
+  // 对象中出现重复 key 时的处理方式，None 时全部保留
+  pub duplicate_keys: Option<DuplicateKeyPolicy>,
+}
+
+impl Default for ParseOptions {
+  fn default() -> Self {
+    Self {
+      allow_single_quotes: false,
+      allow_unquoted_keys: false,
+      allow_bare_strings: false,
+      allow_non_finite: false,
+      allow_comments: false,
+      allow_trailing_commas: false,
+      allow_control_chars: false,
+      reject_number_overflow: false,
+      tab_width: 0,
+      max_string_len: None,
+      max_total_len: None,
+      max_object_entries: None,
+      max_array_entries: None,
+      max_depth: Some(DEFAULT_MAX_DEPTH),
+      attach_comments: false,
+      require_top_level_composite: false,
+      duplicate_keys: None,
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DuplicateKeyPolicy {
+  // 保留第一个，忽略后面重复的
+  FirstWins,
+  // 删除前面的，只保留最后一个
+  LastWins,
+  // 报错，span 指向重复的 key
+  Error,
+}
@@ -1,22 +1,14 @@
-use lazy_static::lazy_static;
-use std::collections::HashMap;
+use std::{borrow::Cow, cmp::Ordering, collections::HashMap};
 
 use crate::{
+  error::{ParseError, ParseErrorKind},
+  escape::unescape_at,
+  options::{DuplicateKeyPolicy, ParseOptions, DEFAULT_MAX_DEPTH},
   span::{Loc, Span},
   tokenizer::Token,
 };
 
-lazy_static! {
-  static ref ESCAPES: HashMap<char, &'static str> = HashMap::from([
-    ('b', r"\b"),
-    ('f', r"\f"),
-    ('n', r"\n"),
-    ('r', r"\r"),
-    ('t', r"\t"),
-  ]);
-}
-
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Ast {
   String(StringAst),
   Number(NumberAst),
@@ -41,151 +33,1114 @@ impl Ast {
       Ast::Array(ast) => &ast.span,
     }
   }
+
+  // 节点在原文中的写法，source 必须是解析出这个节点的输入
+  pub fn source_text<'a>(&self, source: &'a str) -> &'a str {
+    self.get_span().slice(source)
+  }
+
+  // 类似 std::mem::take，取出当前节点，原位置留下一个 null
+  pub fn take(&mut self) -> Ast {
+    self.replace(Ast::from(()))
+  }
+
+  // 类似 std::mem::replace，返回旧的节点
+  pub fn replace(&mut self, ast: Ast) -> Ast {
+    std::mem::replace(self, ast)
+  }
+
+  // 递归地对每个对象调用 sort_keys，按 Unicode 码点排序，数组顺序不变
+  pub fn sort_keys_recursive(&mut self) {
+    match self {
+      Ast::Object(ast) => {
+        ast.sort_keys();
+        for property in ast.value.iter_mut() {
+          property.value.sort_keys_recursive();
+        }
+      }
+      Ast::Property(ast) => ast.value.sort_keys_recursive(),
+      Ast::Array(ast) => {
+        for item in ast.value.iter_mut() {
+          item.sort_keys_recursive();
+        }
+      }
+      _ => {}
+    }
+  }
+
+  pub fn as_object(&self) -> Option<&ObjectAst> {
+    match self {
+      Ast::Object(ast) => Some(ast),
+      _ => None,
+    }
+  }
+
+  pub fn as_array(&self) -> Option<&ArrayAst> {
+    match self {
+      Ast::Array(ast) => Some(ast),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Ast::String(ast) => Some(&ast.value),
+      _ => None,
+    }
+  }
+
+  pub fn as_f64(&self) -> Option<f64> {
+    match self {
+      Ast::Number(ast) => Some(ast.value),
+      _ => None,
+    }
+  }
+
+  pub fn as_bool(&self) -> Option<bool> {
+    match self {
+      Ast::Boolean(ast) => Some(ast.value),
+      _ => None,
+    }
+  }
+
+  pub fn is_null(&self) -> bool {
+    matches!(self, Ast::Null(_))
+  }
+
+  // 只有 {}、[]、"" 是空的，null、数字、布尔值等其他节点都返回 false
+  pub fn is_empty(&self) -> bool {
+    match self {
+      Ast::Object(ast) => ast.is_empty(),
+      Ast::Array(ast) => ast.is_empty(),
+      Ast::String(ast) => ast.value.is_empty(),
+      _ => false,
+    }
+  }
+
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      Ast::String(_) => "string",
+      Ast::Number(_) => "number",
+      Ast::Boolean(_) => "boolean",
+      Ast::Null(_) => "null",
+      Ast::Object(_) => "object",
+      Ast::Property(_) => "property",
+      Ast::Identifier(_) => "identifier",
+      Ast::Array(_) => "array",
+    }
+  }
+
+  // 深度优先、按文档顺序遍历，先产出自身再产出子节点；对象的子节点是各属性的值
+  pub fn walk(&self) -> impl Iterator<Item = &Ast> {
+    let mut stack = vec![self];
+
+    std::iter::from_fn(move || {
+      let ast = stack.pop()?;
+      match ast {
+        Ast::Object(ast) => stack.extend(ast.value.iter().rev().map(|p| p.value.as_ref())),
+        Ast::Array(ast) => stack.extend(ast.value.iter().rev().map(|item| item.as_ref())),
+        Ast::Property(ast) => stack.push(&ast.value),
+        _ => {}
+      }
+      Some(ast)
+    })
+  }
+
+  // 与 walk 顺序相同，f 先处理节点，再继续遍历它（可能已被替换）的子节点
+  pub fn walk_mut(&mut self, mut f: impl FnMut(&mut Ast)) {
+    let mut stack = vec![self];
+
+    while let Some(ast) = stack.pop() {
+      f(ast);
+      match ast {
+        Ast::Object(ast) => stack.extend(ast.value.iter_mut().rev().map(|p| p.value.as_mut())),
+        Ast::Array(ast) => stack.extend(ast.value.iter_mut().rev().map(|item| item.as_mut())),
+        Ast::Property(ast) => stack.push(&mut ast.value),
+        _ => {}
+      }
+    }
+  }
+
+  // 删除所有对象中 f(key, value) 返回 false 的属性，只在保留下来的值中继续向下处理
+  pub fn retain_recursive(&mut self, mut f: impl FnMut(&str, &Ast) -> bool) {
+    self.walk_mut(|ast| {
+      if let Ast::Object(object) = ast {
+        object.retain(|property| f(&property.key.value.value, &property.value));
+      }
+    });
+  }
+
+  // 与 retain_recursive 对应，删除所有数组中 f 返回 false 的元素
+  pub fn retain_items_recursive(&mut self, mut f: impl FnMut(&Ast) -> bool) {
+    self.walk_mut(|ast| {
+      if let Ast::Array(array) = ast {
+        array.value.retain(|item| f(item));
+      }
+    });
+  }
+
+  // 只比较值，忽略 span
+  pub fn value_eq(&self, other: &Ast) -> bool {
+    eq_by(self, other, |a, b| a == b)
+  }
+
+  // 只比较值，忽略 span。不同类型按 null < boolean < number < string < array < object 排序，
+  // 数组、对象按元素逐个比较；-0 与 0 相等，NaN 大于所有数字
+  pub fn cmp_value(&self, other: &Ast) -> Ordering {
+    match (self, other) {
+      (Ast::String(a), Ast::String(b)) => a.value.cmp(&b.value),
+      (Ast::Number(a), Ast::Number(b)) => {
+        normalize_number(a.value).total_cmp(&normalize_number(b.value))
+      }
+      (Ast::Boolean(a), Ast::Boolean(b)) => a.value.cmp(&b.value),
+      (Ast::Null(_), Ast::Null(_)) => Ordering::Equal,
+      (Ast::Object(a), Ast::Object(b)) => a
+        .value
+        .iter()
+        .zip(b.value.iter())
+        .map(|(a, b)| property_cmp_value(a, b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or_else(|| a.value.len().cmp(&b.value.len())),
+      (Ast::Property(a), Ast::Property(b)) => property_cmp_value(a, b),
+      (Ast::Identifier(a), Ast::Identifier(b)) => a.value.value.cmp(&b.value.value),
+      (Ast::Array(a), Ast::Array(b)) => a
+        .value
+        .iter()
+        .zip(b.value.iter())
+        .map(|(a, b)| a.cmp_value(b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or_else(|| a.value.len().cmp(&b.value.len())),
+      _ => type_rank(self).cmp(&type_rank(other)),
+    }
+  }
+
+  // 按源码位置排序，用法：nodes.sort_by(|a, b| Ast::by_span(a, b))
+  pub fn by_span(a: &Ast, b: &Ast) -> Ordering {
+    a.get_span().cmp_offset(b.get_span())
+  }
+
+  // 按 cmp_value 对数组元素做稳定排序，只排当前这一层，不是数组时什么都不做
+  pub fn sort_array_values(&mut self) {
+    if let Ast::Array(ast) = self {
+      ast.value.sort_by(|a, b| a.cmp_value(b));
+    }
+  }
+}
+
+// 对任意节点的借用，PropertyAst、IdentifierAst 不是独立的 Ast，需要通过它来表示
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AstRef<'a> {
+  String(&'a StringAst),
+  Number(&'a NumberAst),
+  Boolean(&'a BoolAst),
+  Null(&'a NullAst),
+  Object(&'a ObjectAst),
+  Property(&'a PropertyAst),
+  Identifier(&'a IdentifierAst),
+  Array(&'a ArrayAst),
+}
+
+impl<'a> From<&'a Ast> for AstRef<'a> {
+  fn from(ast: &'a Ast) -> Self {
+    match ast {
+      Ast::String(ast) => AstRef::String(ast),
+      Ast::Number(ast) => AstRef::Number(ast),
+      Ast::Boolean(ast) => AstRef::Boolean(ast),
+      Ast::Null(ast) => AstRef::Null(ast),
+      Ast::Object(ast) => AstRef::Object(ast),
+      Ast::Property(ast) => AstRef::Property(ast),
+      Ast::Identifier(ast) => AstRef::Identifier(ast),
+      Ast::Array(ast) => AstRef::Array(ast),
+    }
+  }
+}
+
+impl<'a> AstRef<'a> {
+  pub fn get_span(&self) -> &'a Span {
+    match *self {
+      AstRef::String(ast) => &ast.span,
+      AstRef::Number(ast) => &ast.span,
+      AstRef::Boolean(ast) => &ast.span,
+      AstRef::Null(ast) => &ast.span,
+      AstRef::Object(ast) => &ast.span,
+      AstRef::Property(ast) => &ast.span,
+      AstRef::Identifier(ast) => &ast.span,
+      AstRef::Array(ast) => &ast.span,
+    }
+  }
+}
+
+// value_eq 和 ValueHash 共用的结构比较，只有数字的比较方式不同
+pub(crate) fn eq_by(a: &Ast, b: &Ast, number_eq: fn(f64, f64) -> bool) -> bool {
+  match (a, b) {
+    (Ast::String(a), Ast::String(b)) => a.value == b.value,
+    (Ast::Number(a), Ast::Number(b)) => number_eq(a.value, b.value),
+    (Ast::Boolean(a), Ast::Boolean(b)) => a.value == b.value,
+    (Ast::Null(_), Ast::Null(_)) => true,
+    (Ast::Object(a), Ast::Object(b)) => {
+      a.value.len() == b.value.len()
+        && a
+          .value
+          .iter()
+          .zip(b.value.iter())
+          .all(|(a, b)| property_eq_by(a, b, number_eq))
+    }
+    (Ast::Property(a), Ast::Property(b)) => property_eq_by(a, b, number_eq),
+    (Ast::Identifier(a), Ast::Identifier(b)) => a.value.value == b.value.value,
+    (Ast::Array(a), Ast::Array(b)) => {
+      a.value.len() == b.value.len()
+        && a
+          .value
+          .iter()
+          .zip(b.value.iter())
+          .all(|(a, b)| eq_by(a, b, number_eq))
+    }
+    _ => false,
+  }
+}
+
+fn property_eq_by(a: &PropertyAst, b: &PropertyAst, number_eq: fn(f64, f64) -> bool) -> bool {
+  a.key.value.value == b.key.value.value && eq_by(&a.value, &b.value, number_eq)
+}
+
+fn property_cmp_value(a: &PropertyAst, b: &PropertyAst) -> Ordering {
+  a.key
+    .value
+    .value
+    .cmp(&b.key.value.value)
+    .then_with(|| a.value.cmp_value(&b.value))
+}
+
+// 所有 NaN 统一成同一个正 NaN，-0 统一成 0，之后 total_cmp 就与数值大小一致
+fn normalize_number(value: f64) -> f64 {
+  if value.is_nan() {
+    f64::NAN
+  } else if value == 0.0 {
+    0.0
+  } else {
+    value
+  }
 }
 
-#[derive(Debug, PartialEq)]
+fn type_rank(ast: &Ast) -> u8 {
+  match ast {
+    Ast::Null(_) => 0,
+    Ast::Boolean(_) => 1,
+    Ast::Number(_) => 2,
+    Ast::String(_) => 3,
+    Ast::Array(_) => 4,
+    Ast::Object(_) => 5,
+    Ast::Property(_) => 6,
+    Ast::Identifier(_) => 7,
+  }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct StringAst {
   pub value: String,
   pub span: Span,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct NumberAst {
   pub value: f64,
+  // 解析得到的数字保留原始写法，构建的数字可能没有
+  pub raw: Option<String>,
   pub span: Span,
 }
 
-#[derive(Debug, PartialEq)]
+impl NumberAst {
+  // 按原始写法判断：`5` 是整数，`5.0`、`5e0` 不是
+  pub fn is_integer(&self) -> bool {
+    if !self.value.is_finite() {
+      return false;
+    }
+
+    match &self.raw {
+      Some(raw) => !raw.contains(['.', 'e', 'E']),
+      None => self.value.fract() == 0.0,
+    }
+  }
+
+  // 原始的十进制写法，可以交给 rust_decimal、bigdecimal 等无损解析；
+  // 构建的数字没有原始写法时退回 f64 的格式化结果
+  pub fn as_decimal_str(&self) -> Cow<str> {
+    match &self.raw {
+      Some(raw) => Cow::Borrowed(raw),
+      None => Cow::Owned(self.value.to_string()),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct BoolAst {
   pub value: bool,
   pub span: Span,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct NullAst {
   pub span: Span,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct ObjectAst {
   pub value: Vec<PropertyAst>,
   pub span: Span,
 }
 
-#[derive(Debug, PartialEq)]
+impl ObjectAst {
+  // 重复的 key 分别计数
+  pub fn len(&self) -> usize {
+    self.value.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.value.is_empty()
+  }
+
+  // 线性查找，重复 key 时取第一个
+  pub fn get(&self, key: &str) -> Option<&Ast> {
+    self
+      .value
+      .iter()
+      .find(|property| property.key.value.value == key)
+      .map(|property| property.value.as_ref())
+  }
+
+  pub fn get_mut(&mut self, key: &str) -> Option<&mut Ast> {
+    self
+      .value
+      .iter_mut()
+      .find(|property| property.key.value.value == key)
+      .map(|property| property.value.as_mut())
+  }
+
+  pub fn contains_key(&self, key: &str) -> bool {
+    self.get(key).is_some()
+  }
+
+  // 已有的 key 只替换值，否则追加到末尾，返回旧值
+  pub fn insert(&mut self, key: impl Into<String>, value: Ast) -> Option<Ast> {
+    let key = key.into();
+
+    match self.get_mut(&key) {
+      Some(old) => Some(std::mem::replace(old, value)),
+      None => {
+        self.value.push(PropertyAst::new(key, value));
+        None
+      }
+    }
+  }
+
+  pub fn remove(&mut self, key: &str) -> Option<Ast> {
+    let index = self
+      .value
+      .iter()
+      .position(|property| property.key.value.value == key)?;
+
+    Some(*self.value.remove(index).value)
+  }
+
+  pub fn retain(&mut self, f: impl FnMut(&PropertyAst) -> bool) {
+    self.value.retain(f);
+  }
+
+  // 稳定排序，重复的 key 保持原有顺序
+  pub fn sort_keys(&mut self) {
+    self
+      .value
+      .sort_by(|a, b| a.key.value.value.cmp(&b.key.value.value));
+  }
+
+  // 按 key 排序后的属性，与 sort_keys 顺序相同，但不修改对象本身
+  pub fn entries_sorted(&self) -> Vec<&PropertyAst> {
+    let mut entries = self.value.iter().collect::<Vec<&PropertyAst>>();
+    entries.sort_by(|a, b| a.key.value.value.cmp(&b.key.value.value));
+    entries
+  }
+
+  // 供多次查找使用，与 get 不同，重复 key 时取最后一个
+  pub fn as_map(&self) -> HashMap<&str, &Ast> {
+    self
+      .value
+      .iter()
+      .map(|property| (property.key.value.value.as_str(), property.value.as_ref()))
+      .collect()
+  }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct PropertyAst {
   pub key: IdentifierAst,
   pub value: Box<Ast>,
   pub span: Span,
+  // 开启 attach_comments 时，紧挨在前面的注释和同一行末尾的注释，不含 // 和 /* */
+  pub leading_comments: Vec<String>,
+  pub trailing_comments: Vec<String>,
 }
 
-#[derive(Debug, PartialEq)]
+impl PropertyAst {
+  // 新建的节点没有对应的源码位置，span 都是默认值
+  pub fn new(key: impl Into<String>, value: Ast) -> Self {
+    Self {
+      key: IdentifierAst {
+        value: StringAst {
+          value: key.into(),
+          span: Span::default(),
+        },
+        span: Span::default(),
+      },
+      value: Box::new(value),
+      span: Span::default(),
+      leading_comments: vec![],
+      trailing_comments: vec![],
+    }
+  }
+
+  // 解码后的 key，"\u0041" 得到 "A"
+  pub fn key_str(&self) -> &str {
+    &self.key.value.value
+  }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct IdentifierAst {
   pub value: StringAst,
   pub span: Span,
 }
 
-#[derive(Debug, PartialEq)]
+impl IdentifierAst {
+  // 修改解码后的 key，span 保持不变
+  pub fn set_value(&mut self, new_key: &str) {
+    self.value.value = new_key.to_string();
+  }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct ArrayAst {
   pub value: Vec<Box<Ast>>,
   pub span: Span,
 }
 
-enum ObjectState {
-  Start,
-  LeftBrace,
-  Property,
-  Comma,
+impl ArrayAst {
+  pub fn len(&self) -> usize {
+    self.value.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.value.is_empty()
+  }
+
+  pub fn get(&self, index: usize) -> Option<&Ast> {
+    self.value.get(index).map(|item| item.as_ref())
+  }
+
+  pub fn get_mut(&mut self, index: usize) -> Option<&mut Ast> {
+    self.value.get_mut(index).map(|item| item.as_mut())
+  }
+
+  pub fn push(&mut self, value: Ast) {
+    self.value.push(Box::new(value));
+  }
+
+  // index 超出长度时追加到末尾，不会 panic
+  pub fn insert(&mut self, index: usize, value: Ast) {
+    let index = index.min(self.value.len());
+    self.value.insert(index, Box::new(value));
+  }
+
+  pub fn remove(&mut self, index: usize) -> Option<Ast> {
+    if index < self.value.len() {
+      Some(*self.value.remove(index))
+    } else {
+      None
+    }
+  }
 }
 
-enum PropertyState {
-  Start,
+#[derive(Clone, Copy)]
+pub(crate) enum ObjectState {
+  LeftBrace,
+  // 读到 key，等待冒号
   Key,
+  // 读到冒号，等待 value
   Colon,
+  Property,
+  Comma,
 }
 
-enum ArrayState {
-  Start,
+#[derive(Clone, Copy)]
+pub(crate) enum ArrayState {
   LeftBracket,
   Value,
   Comma,
 }
 
+// 当前 object、array 读到一个 token 时要做的事，Parser 和 validate 共用这部分语法
+pub(crate) enum Step {
+  // 读到 } 或 ]，结束当前容器
+  Close,
+  // 读 key，token 不是字符串或标识符时由读 key 的一方报错
+  Key,
+  Colon,
+  Comma,
+  // 开始读一个值
+  Value,
+  Unexpected,
+}
+
+impl ObjectState {
+  pub(crate) fn step(self, token: &Token, allow_trailing_commas: bool) -> Step {
+    match (self, token) {
+      (ObjectState::LeftBrace | ObjectState::Property, Token::RightBrace(_)) => Step::Close,
+      (ObjectState::Comma, Token::RightBrace(_)) if allow_trailing_commas => Step::Close,
+      (ObjectState::LeftBrace | ObjectState::Comma, _) => Step::Key,
+      (ObjectState::Key, Token::Colon(_)) => Step::Colon,
+      (ObjectState::Colon, _) => Step::Value,
+      (ObjectState::Property, Token::Comma(_)) => Step::Comma,
+      _ => Step::Unexpected,
+    }
+  }
+}
+
+impl ArrayState {
+  pub(crate) fn step(self, token: &Token, allow_trailing_commas: bool) -> Step {
+    match (self, token) {
+      (ArrayState::LeftBracket | ArrayState::Value, Token::RightBracket(_)) => Step::Close,
+      (ArrayState::Comma, Token::RightBracket(_)) if allow_trailing_commas => Step::Close,
+      (ArrayState::LeftBracket | ArrayState::Comma, _) => Step::Value,
+      (ArrayState::Value, Token::Comma(_)) => Step::Comma,
+      _ => Step::Unexpected,
+    }
+  }
+}
+
+// 正在解析的 object、array，代替递归调用时的栈帧
+enum Frame<'a> {
+  Object {
+    start_span: &'a Span,
+    object: ObjectAst,
+    state: ObjectState,
+    // 已读到的 key，以及它的 token 下标（用于查找前面的注释）
+    key: Option<(IdentifierAst, usize)>,
+    keys: Option<KeyIndex>,
+  },
+  Array {
+    start_span: &'a Span,
+    array: ArrayAst,
+    state: ArrayState,
+  },
+}
+
+// 开启 duplicate_keys 时记录每个 key 在 object 中的下标，避免每次线性查找。
+// LastWins 被覆盖的 property 先留在原位，object 结束时再一次性删除
+#[derive(Default)]
+struct KeyIndex {
+  indices: HashMap<String, usize>,
+  removed: Vec<usize>,
+}
+
+impl KeyIndex {
+  fn compact(self, object: &mut ObjectAst) {
+    if self.removed.is_empty() {
+      return;
+    }
+
+    let mut removed = vec![false; object.len()];
+    for index in self.removed {
+      removed[index] = true;
+    }
+    let mut index = 0;
+    object.retain(|_| {
+      index += 1;
+      !removed[index - 1]
+    });
+  }
+}
+
 pub(crate) struct Parser<'a> {
   tokens: &'a [Token],
-  len: usize,
   index: usize,
+  // 允许 [1, 2,] 和 {"a": 1,} 这样的尾逗号
+  allow_trailing_commas: bool,
+  // 把注释挂到 property 上，需要 token 中保留 trivia
+  attach_comments: bool,
+  // 遇到语法错误时记录下来并跳到下一个 , } ] 继续解析
+  recovering: bool,
+  errors: Vec<ParseError>,
+  duplicate_keys: Option<DuplicateKeyPolicy>,
+  // 值的位置出现的标识符当作字符串
+  allow_bare_strings: bool,
+  // 字符串中允许 \' 转义
+  allow_single_quotes: bool,
+  max_object_entries: Option<usize>,
+  max_array_entries: Option<usize>,
+  max_depth: Option<usize>,
+  require_top_level_composite: bool,
 }
 
 impl<'a> Parser<'a> {
   pub fn new(tokens: &'a [Token]) -> Self {
     Self {
       tokens,
-      len: tokens.len(),
       index: 0,
+      allow_trailing_commas: false,
+      attach_comments: false,
+      recovering: false,
+      errors: vec![],
+      duplicate_keys: None,
+      allow_bare_strings: false,
+      allow_single_quotes: false,
+      max_object_entries: None,
+      max_array_entries: None,
+      max_depth: Some(DEFAULT_MAX_DEPTH),
+      require_top_level_composite: false,
+    }
+  }
+
+  pub fn with_options(tokens: &'a [Token], options: &ParseOptions) -> Self {
+    Self {
+      allow_trailing_commas: options.allow_trailing_commas,
+      attach_comments: options.attach_comments,
+      duplicate_keys: options.duplicate_keys,
+      allow_bare_strings: options.allow_bare_strings,
+      allow_single_quotes: options.allow_single_quotes,
+      max_object_entries: options.max_object_entries,
+      max_array_entries: options.max_array_entries,
+      max_depth: options.max_depth,
+      require_top_level_composite: options.require_top_level_composite,
+      ..Self::new(tokens)
+    }
+  }
+
+  pub fn parse(&mut self) -> Result<Ast, ParseError> {
+    if self.current().is_none() {
+      return Err(ParseError::empty_input());
+    }
+
+    let ast = self.parse_value()?;
+    self.check_top_level(ast)
+  }
+
+  // 尽量解析完整个输入，收集所有语法错误，出错的数组元素用 null 占位
+  pub fn parse_recovering(mut self) -> (Option<Ast>, Vec<ParseError>) {
+    self.recovering = true;
+
+    let ast = match self.parse() {
+      Ok(ast) => Some(ast),
+      Err(error) => {
+        self.errors.push(error);
+        None
+      }
+    };
+
+    (ast, self.errors)
+  }
+
+  // 依次解析多个相邻的值，直到 token 耗尽
+  pub fn parse_many(&mut self) -> Result<Vec<Ast>, ParseError> {
+    let mut values = vec![];
+
+    while self.current().is_some() {
+      let value = self.parse_value()?;
+      values.push(self.check_top_level(value)?);
+    }
+
+    Ok(values)
+  }
+
+  fn error_eof(&self) -> Result<Ast, ParseError> {
+    Err(self.eof())
+  }
+
+  fn check_top_level(&self, ast: Ast) -> Result<Ast, ParseError> {
+    if self.require_top_level_composite && !matches!(ast, Ast::Object(_) | Ast::Array(_)) {
+      return Err(top_level_not_composite(ast.get_span()));
     }
+
+    Ok(ast)
+  }
+
+  // 跳过 trivia，返回当前 token
+  fn current(&mut self) -> Option<&'a Token> {
+    while let Some(token) = self.tokens.get(self.index) {
+      if !token.is_trivia() {
+        return Some(token);
+      }
+      self.index += 1;
+    }
+
+    None
+  }
+
+  // key 前面的注释，跟在上一个 property 逗号同一行的注释属于上一个 property
+  fn leading_comments(&self, key_index: usize) -> Vec<String> {
+    let before = &self.tokens[..key_index];
+    let start = before
+      .iter()
+      .rposition(|token| !token.is_trivia())
+      .map_or(0, |index| index + 1);
+    let prev_line = match start.checked_sub(1).map(|index| &before[index]) {
+      Some(Token::Comma(token)) => Some(token.span.end.line),
+      _ => None,
+    };
+
+    before[start..]
+      .iter()
+      .filter_map(|token| match token {
+        Token::Comment(comment) if Some(comment.span.start.line) != prev_line => {
+          Some(comment_text(&comment.value))
+        }
+        _ => None,
+      })
+      .collect()
   }
 
-  pub fn parse(&mut self) -> Result<Ast, String> {
-    if self.len == 0 {
-      return self.error_eof();
+  // 值后面（可以隔一个逗号）同一行的注释
+  fn trailing_comments(&self, value_span: &Span) -> Vec<String> {
+    let mut comments = vec![];
+    let line = value_span.end.line;
+
+    for token in &self.tokens[self.index..] {
+      match token {
+        Token::Comment(comment) if comment.span.start.line == line => {
+          comments.push(comment_text(&comment.value))
+        }
+        Token::Whitespace(token) if token.span.end.line == line => {}
+        Token::Comma(_) if comments.is_empty() => {}
+        _ => break,
+      }
     }
 
-    self.parse_value()
+    comments
   }
 
-  fn error_eof(&self) -> Result<Ast, String> {
-    Err(format!("Unexpected end of input"))
+  fn eof(&self) -> ParseError {
+    ParseError::eof(
+      self
+        .tokens
+        .iter()
+        .rev()
+        .find(|token| !token.is_trivia())
+        .map(|token| token.get_span().clone()),
+    )
   }
 
-  fn error_token(&self, token: &Token) -> Result<Ast, String> {
-    Err(format!("Unexpected token: {:#?}", token))
+  fn error_token(&self, token: &Token) -> Result<Ast, ParseError> {
+    Err(unexpected_token(token))
+  }
+
+  // 记录错误并跳到同一层级的下一个 , 或当前容器的结束符，非恢复模式下直接返回错误
+  fn recover(&mut self, error: ParseError, in_array: bool) -> Result<(), ParseError> {
+    if !self.recovering
+      || matches!(
+        error.kind,
+        ParseErrorKind::UnexpectedEof | ParseErrorKind::LimitExceeded
+      )
+    {
+      return Err(error);
+    }
+    self.errors.push(error);
+
+    let mut depth = 0;
+
+    while let Some(token) = self.current() {
+      match token {
+        Token::LeftBrace(_) | Token::LeftBracket(_) => depth += 1,
+        Token::RightBrace(_) | Token::RightBracket(_) if depth > 0 => depth -= 1,
+        Token::Comma(_) if depth == 0 => return Ok(()),
+        Token::RightBracket(_) if in_array => return Ok(()),
+        Token::RightBrace(_) if !in_array => return Ok(()),
+        // 不匹配的结束符直接跳过
+        _ => {}
+      }
+      self.index += 1;
+    }
+
+    Err(self.eof())
+  }
+
+  fn add_property(
+    &mut self,
+    object: &mut ObjectAst,
+    keys: Option<&mut KeyIndex>,
+    property: PropertyAst,
+  ) -> Result<(), ParseError> {
+    let span = property.span.clone();
+
+    // 没有被覆盖的 property 个数
+    let len = match keys {
+      None => {
+        object.value.push(property);
+        object.len()
+      }
+      Some(keys) => {
+        // 用解码后的 key 判断重复，"\u0041" 与 "A" 是同一个 key
+        let key = &property.key.value.value;
+
+        match (keys.indices.get(key), self.duplicate_keys) {
+          (None, _) => {
+            keys.indices.insert(key.clone(), object.len());
+            object.value.push(property);
+          }
+          (Some(&index), Some(DuplicateKeyPolicy::LastWins)) => {
+            keys.removed.push(index);
+            keys.indices.insert(key.clone(), object.len());
+            object.value.push(property);
+          }
+          (Some(_), Some(DuplicateKeyPolicy::Error)) => {
+            let error = duplicate_key(key, &property.key.span);
+            self.recover(error, false)?;
+          }
+          (Some(_), _) => {}
+        }
+        object.len() - keys.removed.len()
+      }
+    };
+
+    match self.max_object_entries {
+      Some(max) if len > max => Err(object_limit_exceeded(max, &span)),
+      _ => Ok(()),
+    }
   }
 
   fn create_span(&self, start_span: Option<&Span>, end_span: &Span) -> Span {
-    if let Some(start_span) = start_span {
-      return Span {
-        start: Loc {
-          line: start_span.start.line,
-          column: start_span.start.column,
-          offset: start_span.start.offset,
+    match start_span {
+      Some(start_span) => start_span.merge(end_span),
+      None => end_span.clone(),
+    }
+  }
+
+  // literal, object, array
+  // object、array 不递归解析，而是压入 stack，嵌套深度由 max_depth 限制
+  fn parse_value(&mut self) -> Result<Ast, ParseError> {
+    let mut stack = vec![];
+    // 刚解析完的值或错误，交给栈顶的 object、array
+    let mut result = self.begin_value(&mut stack);
+
+    loop {
+      if let Some(value) = result.take() {
+        match stack.last_mut() {
+          None => return value,
+          Some(Frame::Object {
+            object,
+            state,
+            key,
+            keys,
+            ..
+          }) => {
+            let (identifier, key_index) = key.take().unwrap();
+            match value {
+              Ok(value) => {
+                let property = self.create_property(identifier, key_index, value);
+                self.add_property(object, keys.as_mut(), property)?;
+              }
+              Err(error) => self.recover(error, false)?,
+            }
+            *state = ObjectState::Property;
+          }
+          Some(Frame::Array { array, state, .. }) => {
+            let value = match value {
+              Ok(value) => value,
+              Err(error) => {
+                // 出错的元素用 null 占位
+                let span = error.span.clone().unwrap_or_default();
+                self.recover(error, true)?;
+                Ast::Null(NullAst { span })
+              }
+            };
+            if let Some(max) = self.max_array_entries.filter(|max| array.len() >= *max) {
+              return Err(array_limit_exceeded(max, value.get_span()));
+            }
+            array.push(value);
+            *state = ArrayState::Value;
+          }
+        }
+        continue;
+      }
+
+      let Some(token) = self.current() else {
+        return self.error_eof();
+      };
+
+      match stack.last_mut().unwrap() {
+        Frame::Object { state, key, .. } => match state.step(token, self.allow_trailing_commas) {
+          Step::Close => result = Some(Ok(self.close(&mut stack, token.get_span()))),
+          Step::Key => match self.parse_key(token) {
+            Ok(identifier) => {
+              *key = Some((identifier, self.index));
+              *state = ObjectState::Key;
+              self.index += 1;
+            }
+            Err(error) => {
+              *state = ObjectState::Property;
+              self.recover(error, false)?;
+            }
+          },
+          Step::Colon => {
+            *state = ObjectState::Colon;
+            self.index += 1;
+          }
+          Step::Comma => {
+            *state = ObjectState::Comma;
+            self.index += 1;
+          }
+          Step::Value => result = self.begin_value(&mut stack),
+          Step::Unexpected => {
+            if let ObjectState::Key = state {
+              *key = None;
+              *state = ObjectState::Property;
+            }
+            self.recover(unexpected_token(token), false)?;
+          }
         },
-        end: Loc {
-          line: end_span.end.line,
-          column: end_span.end.column,
-          offset: end_span.end.offset,
+        Frame::Array { state, .. } => match state.step(token, self.allow_trailing_commas) {
+          Step::Close => result = Some(Ok(self.close(&mut stack, token.get_span()))),
+          Step::Value => result = self.begin_value(&mut stack),
+          Step::Comma => {
+            *state = ArrayState::Comma;
+            self.index += 1;
+          }
+          _ => self.recover(unexpected_token(token), true)?,
         },
-      };
-    } else {
-      return end_span.clone();
+      }
     }
   }
 
-  // literal, object, array
-  fn parse_value(&mut self) -> Result<Ast, String> {
-    self
-      .parse_literal()
-      .or_else(|_| self.parse_object())
-      .or_else(|_| self.parse_array())
+  // 遇到 { 或 [ 时压栈并返回 None，否则直接解析出字面量
+  fn begin_value(&mut self, stack: &mut Vec<Frame<'a>>) -> Option<Result<Ast, ParseError>> {
+    if let Some(token @ (Token::LeftBrace(_) | Token::LeftBracket(_))) = self.current() {
+      if let Some(max) = self.max_depth.filter(|max| stack.len() >= *max) {
+        return Some(Err(depth_exceeded(max, token.get_span())));
+      }
+    }
+
+    match self.current() {
+      Some(Token::LeftBrace(token)) => {
+        self.index += 1;
+        stack.push(Frame::Object {
+          start_span: &token.span,
+          object: ObjectAst {
+            value: vec![],
+            span: Span::default(),
+          },
+          state: ObjectState::LeftBrace,
+          key: None,
+          keys: self.duplicate_keys.map(|_| KeyIndex::default()),
+        });
+        None
+      }
+      Some(Token::LeftBracket(token)) => {
+        self.index += 1;
+        stack.push(Frame::Array {
+          start_span: &token.span,
+          array: ArrayAst {
+            value: vec![],
+            span: Span::default(),
+          },
+          state: ArrayState::LeftBracket,
+        });
+        None
+      }
+      Some(_) => Some(self.parse_literal()),
+      None => Some(self.error_eof()),
+    }
+  }
+
+  // 读到结束的 } 或 ]，弹出栈顶并生成对应的节点
+  fn close(&mut self, stack: &mut Vec<Frame<'a>>, end_span: &Span) -> Ast {
+    self.index += 1;
+
+    match stack.pop().unwrap() {
+      Frame::Object {
+        start_span,
+        mut object,
+        keys,
+        ..
+      } => {
+        if let Some(keys) = keys {
+          keys.compact(&mut object);
+        }
+        object.span = self.create_span(Some(start_span), end_span);
+        Ast::Object(object)
+      }
+      Frame::Array {
+        start_span,
+        mut array,
+        ..
+      } => {
+        array.span = self.create_span(Some(start_span), end_span);
+        Ast::Array(array)
+      }
+    }
+  }
+
+  fn parse_key(&self, token: &Token) -> Result<IdentifierAst, ParseError> {
+    let (value, span) = match token {
+      Token::String(token) => (
+        parse_string(&token.value, &token.span, self.allow_single_quotes)?,
+        &token.span,
+      ),
+      Token::Identifier(token) => (token.value.clone(), &token.span),
+      _ => return Err(unexpected_token(token)),
+    };
+
+    Ok(IdentifierAst {
+      value: StringAst {
+        value,
+        span: span.clone(),
+      },
+      span: span.clone(),
+    })
+  }
+
+  fn create_property(&self, key: IdentifierAst, key_index: usize, value: Ast) -> PropertyAst {
+    let value_span = value.get_span().clone();
+
+    let (leading_comments, trailing_comments) = if self.attach_comments {
+      (
+        self.leading_comments(key_index),
+        self.trailing_comments(&value_span),
+      )
+    } else {
+      (vec![], vec![])
+    };
+
+    PropertyAst {
+      span: self.create_span(Some(&key.span), &value_span),
+      key,
+      value: Box::new(value),
+      leading_comments,
+      trailing_comments,
+    }
   }
 
   // string, number, boolean, null
-  fn parse_literal(&mut self) -> Result<Ast, String> {
-    let token = self.tokens.get(self.index).unwrap();
+  fn parse_literal(&mut self) -> Result<Ast, ParseError> {
+    let token = self.current().unwrap();
 
     match token {
       Token::String(token) => {
-        let ret = parse_string(&token.value)?;
+        let ret = parse_string(&token.value, &token.span, self.allow_single_quotes)?;
         self.index += 1;
         Ok(Ast::String(StringAst {
           value: ret,
           span: self.create_span(None, &token.span),
         }))
       }
+      Token::Identifier(token) if self.allow_bare_strings => {
+        self.index += 1;
+        Ok(Ast::String(StringAst {
+          value: token.value.clone(),
+          span: self.create_span(None, &token.span),
+        }))
+      }
       Token::Number(token) => {
         self.index += 1;
         Ok(Ast::Number(NumberAst {
           value: token.value,
+          raw: Some(token.raw.clone()),
           span: self.create_span(None, &token.span),
         }))
       }
@@ -202,210 +1157,673 @@ impl<'a> Parser<'a> {
           span: self.create_span(None, &token.span),
         }))
       }
-      _ => Err(format!("Unexpected token: {:#?}", token)),
+      _ => self.error_token(token),
     }
   }
+}
 
-  fn parse_object(&mut self) -> Result<Ast, String> {
-    let mut state = ObjectState::Start;
-    let mut start_span: Option<&Span> = None;
+// 去掉注释的 // 或 /* */ 以及两边的空白
+fn comment_text(comment: &str) -> String {
+  let text = match comment.strip_prefix("//") {
+    Some(text) => text,
+    None => &comment[2..comment.len() - 2],
+  };
 
-    let mut object_ast = ObjectAst {
-      value: vec![],
-      span: Span::default(),
+  text.trim().to_string()
+}
+
+pub(crate) fn unexpected_token(token: &Token) -> ParseError {
+  ParseError::new(
+    format!("Unexpected {}", token.name()),
+    token.get_span().clone(),
+  )
+}
+
+pub(crate) fn top_level_not_composite(span: &Span) -> ParseError {
+  ParseError::new("Expected an object or array at the top level", span.clone())
+}
+
+pub(crate) fn duplicate_key(key: &str, span: &Span) -> ParseError {
+  ParseError::new(format!("Duplicate key \"{}\"", key), span.clone())
+}
+
+pub(crate) fn depth_exceeded(max: usize, span: &Span) -> ParseError {
+  ParseError::limit_exceeded(
+    format!("Nesting exceeds the maximum depth of {}", max),
+    Some(span.clone()),
+  )
+}
+
+pub(crate) fn object_limit_exceeded(max: usize, span: &Span) -> ParseError {
+  ParseError::limit_exceeded(
+    format!("Object exceeds the maximum of {} entries", max),
+    Some(span.clone()),
+  )
+}
+
+pub(crate) fn array_limit_exceeded(max: usize, span: &Span) -> ParseError {
+  ParseError::limit_exceeded(
+    format!("Array exceeds the maximum of {} entries", max),
+    Some(span.clone()),
+  )
+}
+
+pub(crate) fn parse_string(
+  quoted_input: &str,
+  span: &Span,
+  allow_single_quotes: bool,
+) -> Result<String, ParseError> {
+  // 去除首尾引号，位置从开头引号之后算起
+  unescape_at(
+    &quoted_input[1..quoted_input.len() - 1],
+    Loc {
+      line: span.start.line,
+      column: span.start.column + 1,
+      offset: span.start.offset + 1,
+    },
+    allow_single_quotes,
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use std::ops::ControlFlow;
+
+  use crate::{visit::Visit, Json};
+
+  use super::*;
+
+  fn parse_object(input: &str) -> ObjectAst {
+    match Json::parse(input).unwrap() {
+      Ast::Object(object) => object,
+      _ => panic!("expected an object"),
+    }
+  }
+
+  #[test]
+  fn test_as_accessors() {
+    let json = crate::Json::parse(
+      "{\"o\": {\"x\": 1}, \"a\": [1, 2], \"s\": \"hi\", \"n\": 1.5, \"b\": false, \"z\": null}",
+    )
+    .unwrap();
+    let object = json.as_object().unwrap();
+
+    let o = object.get("o").unwrap();
+    assert_eq!(o.as_object().unwrap().get("x").unwrap().as_f64(), Some(1.0));
+    assert_eq!(object.get("a").unwrap().as_array().unwrap().value.len(), 2);
+    assert_eq!(object.get("s").unwrap().as_str(), Some("hi"));
+    assert_eq!(object.get("n").unwrap().as_f64(), Some(1.5));
+    assert_eq!(object.get("b").unwrap().as_bool(), Some(false));
+    assert!(object.get("z").unwrap().is_null());
+
+    let s = object.get("s").unwrap();
+    assert!(s.as_object().is_none());
+    assert!(s.as_array().is_none());
+    assert!(s.as_f64().is_none());
+    assert!(s.as_bool().is_none());
+    assert!(!s.is_null());
+    assert!(o.as_str().is_none());
+    assert!(json.as_array().is_none());
+  }
+
+  #[test]
+  fn test_object_get() {
+    let mut object = parse_object("{\"a\": 1, \"b\": true, \"a\": 2}");
+
+    assert!(matches!(object.get("a"), Some(Ast::Number(n)) if n.value == 1.0));
+    assert!(matches!(object.get("b"), Some(Ast::Boolean(b)) if b.value));
+    assert_eq!(object.get("c"), None);
+    assert!(object.contains_key("b"));
+    assert!(!object.contains_key("c"));
+
+    if let Some(Ast::Number(n)) = object.get_mut("a") {
+      n.value = 3.0;
+    }
+    assert!(matches!(object.get("a"), Some(Ast::Number(n)) if n.value == 3.0));
+  }
+
+  #[test]
+  fn test_object_insert_remove() {
+    let mut json = crate::Json::parse("{\"a\": 1, \"b\": [true]}").unwrap();
+    let Ast::Object(object) = &mut json else {
+      panic!("expected an object");
     };
 
-    while let Some(token) = self.tokens.get(self.index) {
-      match state {
-        ObjectState::Start => match token {
-          Token::LeftBrace(token) => {
-            state = ObjectState::LeftBrace;
-            start_span = Some(&token.span);
-            self.index += 1;
-          }
-          _ => {
-            return self.error_token(token);
-          }
-        },
-        ObjectState::LeftBrace => match token {
-          Token::RightBrace(token) => {
-            self.index += 1;
-            object_ast.span = self.create_span(start_span, &token.span);
-            return Ok(Ast::Object(object_ast));
-          }
-          _ => {
-            if let Ast::Property(property) = self.parse_property()? {
-              object_ast.value.push(property);
-              state = ObjectState::Property;
-            } else {
-              return self.error_token(token);
-            }
-          }
-        },
-        ObjectState::Property => match token {
-          Token::Comma(_) => {
-            state = ObjectState::Comma;
-            self.index += 1;
-          }
-          Token::RightBrace(token) => {
-            self.index += 1;
-            object_ast.span = self.create_span(start_span, &token.span);
-            return Ok(Ast::Object(object_ast));
-          }
-          _ => return self.error_token(token),
-        },
-        ObjectState::Comma => {
-          if let Ast::Property(property) = self.parse_property()? {
-            object_ast.value.push(property);
-            state = ObjectState::Property;
-          } else {
-            return self.error_token(token);
-          }
-        }
-      }
+    let old = object.insert("a", Ast::from("x"));
+    assert_eq!(old.unwrap().as_f64(), Some(1.0));
+    assert_eq!(object.value[0].span.start.offset, 1);
+    assert_eq!(object.get("a").unwrap().get_span(), &Span::default());
+
+    assert!(object.insert("c", Ast::from(())).is_none());
+    assert_eq!(object.value[2].span, Span::default());
+
+    let removed = object.remove("b").unwrap();
+    assert!(removed.value_eq(&crate::json!([true])));
+    assert!(object.remove("b").is_none());
+    assert!(object.remove("missing").is_none());
+
+    *object.get_mut("c").unwrap() = Ast::from(2i64);
+    assert_eq!(json.to_string().unwrap(), "{\"a\":\"x\",\"c\":2}");
+  }
+
+  #[test]
+  fn test_array_mutation() {
+    let mut json = crate::Json::parse("[1, 2]").unwrap();
+    let Ast::Array(array) = &mut json else {
+      panic!("expected an array");
+    };
+
+    array.push(Ast::from("end"));
+    array.insert(1, Ast::from(true));
+    array.insert(100, Ast::from(()));
+    assert_eq!(array.get(0).unwrap().get_span().start.offset, 1);
+    assert_eq!(array.get(1).unwrap().get_span(), &Span::default());
+
+    assert_eq!(array.remove(2).unwrap().as_f64(), Some(2.0));
+    assert!(array.remove(4).is_none());
+    assert_eq!(array.value.len(), 4);
+
+    *array.get_mut(0).unwrap() = Ast::from(0i64);
+    assert!(array.get_mut(4).is_none());
+    assert_eq!(json.to_string().unwrap(), "[0,true,\"end\",null]");
+  }
+
+  #[test]
+  fn test_object_as_map() {
+    let object = parse_object("{\"a\": 1, \"b\": true, \"a\": 2}");
+    let map = object.as_map();
+
+    assert_eq!(map.len(), 2);
+    assert!(matches!(map["a"], Ast::Number(n) if n.value == 2.0));
+    assert!(matches!(map["b"], Ast::Boolean(b) if b.value));
+  }
+
+  #[test]
+  fn test_object_as_map_large() {
+    let input = format!(
+      "{{{}}}",
+      (0..10_000)
+        .map(|i| format!("\"key{}\": {}", i, i))
+        .collect::<Vec<_>>()
+        .join(",")
+    );
+    let object = parse_object(&input);
+    let map = object.as_map();
+
+    assert_eq!(map.len(), 10_000);
+    for i in (0..10_000).step_by(7) {
+      let key = format!("key{}", i);
+      assert!(matches!(map[key.as_str()], Ast::Number(n) if n.value == i as f64));
     }
+  }
 
-    self.error_eof()
+  #[test]
+  fn test_duplicate_keys_large() {
+    // 每个 key 出现两次，第二轮的顺序反过来
+    let input = format!(
+      "{{{}}}",
+      (0..20_000)
+        .chain((0..20_000).rev())
+        .enumerate()
+        .map(|(i, key)| format!("\"key{}\": {}", key, i))
+        .collect::<Vec<_>>()
+        .join(",")
+    );
+    let parse = |duplicate_keys| {
+      let options = ParseOptions {
+        duplicate_keys: Some(duplicate_keys),
+        max_object_entries: Some(20_000),
+        ..ParseOptions::default()
+      };
+      match Json::parse_with_options(&input, &options).unwrap() {
+        Ast::Object(object) => object,
+        _ => unreachable!(),
+      }
+    };
+
+    let object = parse(DuplicateKeyPolicy::FirstWins);
+    assert_eq!(object.len(), 20_000);
+    assert_eq!(object.value[0].key.value.value, "key0");
+    assert!(matches!(object.get("key0"), Some(Ast::Number(n)) if n.value == 0.0));
+
+    // 保留最后一个，顺序按最后出现的位置
+    let object = parse(DuplicateKeyPolicy::LastWins);
+    assert_eq!(object.len(), 20_000);
+    assert_eq!(object.value[0].key.value.value, "key19999");
+    assert!(matches!(object.get("key0"), Some(Ast::Number(n)) if n.value == 39_999.0));
   }
 
-  fn parse_property(&mut self) -> Result<Ast, String> {
-    let mut state = PropertyState::Start;
-    let mut start_span: Option<&Span> = None;
-    let mut identifier: Option<IdentifierAst> = None;
+  #[test]
+  fn test_object_retain_in_visitor() {
+    struct RemoveNulls;
 
-    while let Some(token) = self.tokens.get(self.index) {
-      match state {
-        PropertyState::Start => match token {
-          Token::String(token) => {
-            start_span = Some(&token.span);
-            identifier = Some(IdentifierAst {
-              value: StringAst {
-                value: parse_string(&token.value)?,
-                span: token.span.clone(),
-              },
-              span: token.span.clone(),
-            });
-            state = PropertyState::Key;
-            self.index += 1;
-          }
-          _ => return self.error_token(token),
-        },
-        PropertyState::Key => match token {
-          Token::Colon(_) => {
-            state = PropertyState::Colon;
-            self.index += 1;
-          }
-          _ => return self.error_token(token),
-        },
-        PropertyState::Colon => {
-          let value = self.parse_value()?;
-          let value_span = value.get_span().clone();
-
-          return Ok(Ast::Property(PropertyAst {
-            key: identifier.unwrap(),
-            value: Box::new(value),
-            span: self.create_span(start_span, &value_span),
-          }));
+    impl Visit for RemoveNulls {
+      fn visit_object(&mut self, ast: &mut ObjectAst) -> ControlFlow<()> {
+        ast.retain(|property| !matches!(*property.value, Ast::Null(_)));
+
+        for property in ast.value.iter_mut() {
+          self.visit_property(property)?;
         }
+
+        ControlFlow::Continue(())
       }
     }
 
-    self.error_eof()
+    let mut json =
+      Json::parse("{\"a\": null, \"b\": {\"c\": null, \"d\": 1}, \"e\": [null]}").unwrap();
+    let _ = RemoveNulls.visit_json(&mut json);
+
+    assert_eq!(json.to_string().unwrap(), "{\"b\":{\"d\":1},\"e\":[null]}");
   }
 
-  fn parse_array(&mut self) -> Result<Ast, String> {
-    let mut state = ArrayState::Start;
-    let mut start_span: Option<&Span> = None;
-    let mut array_value = vec![];
+  #[test]
+  fn test_object_sort_keys() {
+    let mut object = parse_object("{\"b\": 1, \"a\": 2, \"c\": 3, \"a\": 4}");
+    object.sort_keys();
 
-    while let Some(token) = self.tokens.get(self.index) {
-      match state {
-        ArrayState::Start => match token {
-          Token::LeftBracket(token) => {
-            start_span = Some(&token.span);
-            state = ArrayState::LeftBracket;
-            self.index += 1;
-          }
-          _ => return self.error_token(token),
-        },
-        ArrayState::LeftBracket => match token {
-          Token::RightBracket(token) => {
-            self.index += 1;
-            return Ok(Ast::Array(ArrayAst {
-              value: array_value,
-              span: self.create_span(start_span, &token.span),
-            }));
-          }
-          _ => {
-            let value = self.parse_value()?;
-            array_value.push(Box::new(value));
-            state = ArrayState::Value;
-          }
-        },
-        ArrayState::Value => match token {
-          Token::RightBracket(token) => {
-            self.index += 1;
-            return Ok(Ast::Array(ArrayAst {
-              value: array_value,
-              span: self.create_span(start_span, &token.span),
-            }));
-          }
-          Token::Comma(_) => {
-            state = ArrayState::Comma;
-            self.index += 1;
-          }
-          _ => return self.error_token(token),
-        },
-        ArrayState::Comma => {
-          let value = self.parse_value()?;
-          array_value.push(Box::new(value));
-          state = ArrayState::Value;
-        }
-      }
+    assert_eq!(
+      Ast::Object(object).to_string().unwrap(),
+      "{\"a\":2,\"a\":4,\"b\":1,\"c\":3}"
+    );
+  }
+
+  #[test]
+  fn test_sort_keys_recursive() {
+    let mut a =
+      crate::Json::parse("{\"b\": [{\"y\": 1, \"x\": 2}, 3], \"a\": {\"é\": 1, \"z\": 2}}")
+        .unwrap();
+    let mut b =
+      crate::Json::parse("{\"a\": {\"z\": 2, \"é\": 1}, \"b\": [{\"x\": 2, \"y\": 1}, 3]}")
+        .unwrap();
+    assert!(!a.value_eq(&b));
+
+    a.sort_keys_recursive();
+    b.sort_keys_recursive();
+
+    assert_eq!(a.to_string().unwrap(), b.to_string().unwrap());
+    assert_eq!(
+      a.to_string().unwrap(),
+      "{\"a\":{\"z\":2,\"é\":1},\"b\":[{\"x\":2,\"y\":1},3]}"
+    );
+  }
+
+  #[test]
+  fn test_sort_array_values() {
+    let mut json =
+      crate::Json::parse("[\"b\", 2, {\"a\": 1}, null, true, [1], \"a\", -0, false, 0.5, [], 0]")
+        .unwrap();
+    json.sort_array_values();
+
+    assert_eq!(
+      json.to_string().unwrap(),
+      "[null,false,true,-0,0,0.5,2,\"a\",\"b\",[],[1],{\"a\":1}]"
+    );
+  }
+
+  #[test]
+  fn test_max_depth() {
+    let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+
+    // 默认上限内的文档，其他递归实现的方法也能处理
+    let input = nested(DEFAULT_MAX_DEPTH);
+    let json = Json::parse(&input).unwrap();
+    assert_eq!(json.depth(), DEFAULT_MAX_DEPTH);
+    assert!(Json::validate(&input).is_ok());
+    assert_eq!(json.to_string().unwrap(), input);
+    assert!(json.to_string_pretty().is_ok());
+    assert!(json.value_eq(&json.clone()));
+
+    let input = nested(DEFAULT_MAX_DEPTH + 1);
+    let err = Json::parse(&input).unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::LimitExceeded);
+    assert_eq!(err.message, "Nesting exceeds the maximum depth of 128");
+    assert_eq!(err.span.as_ref().unwrap().start.offset, DEFAULT_MAX_DEPTH);
+    assert_eq!(Json::validate(&input).unwrap_err(), err);
+    assert_eq!(Json::parse_recovering(&input), (None, vec![err]));
+
+    // 对象和数组一起计数
+    let options = ParseOptions {
+      max_depth: Some(2),
+      ..ParseOptions::default()
+    };
+    assert!(Json::parse_with_options("{\"a\": [1], \"b\": {}}", &options).is_ok());
+    let err = Json::parse_with_options("[{\"a\": []}]", &options).unwrap_err();
+    assert_eq!(err.span.unwrap().start.offset, 7);
+
+    // 解析本身不递归，关闭上限后校验很深的输入也不会爆栈
+    let options = ParseOptions {
+      max_depth: None,
+      ..ParseOptions::default()
+    };
+    assert!(Json::validate_with_options(&nested(1_000_000), &options).is_ok());
+    let err = Json::parse_with_options(&"[{\"a\":".repeat(100_000), &options).unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::UnexpectedEof);
+  }
+
+  #[test]
+  fn test_is_empty() {
+    let json = |input: &str| crate::Json::parse(input).unwrap();
+
+    assert!(json("{}").is_empty());
+    assert!(json("[]").is_empty());
+    assert!(json("\"\"").is_empty());
+    assert!(!json("{\"a\": {}}").is_empty());
+    assert!(!json("[[]]").is_empty());
+    assert!(!json("\" \"").is_empty());
+
+    for input in ["null", "0", "false", "true"] {
+      assert!(!json(input).is_empty());
     }
 
-    self.error_eof()
+    let object = json("{\"a\": 1, \"b\": 2, \"a\": 3}");
+    assert_eq!(object.as_object().unwrap().len(), 3);
+    assert_eq!(json("{}").as_object().unwrap().len(), 0);
+    assert_eq!(json("[1, [2, 3]]").as_array().unwrap().len(), 2);
+    assert!(json("[]").as_array().unwrap().is_empty());
   }
-}
 
-fn parse_string(quoted_input: &str) -> Result<String, String> {
-  let mut ret = String::new();
+  #[test]
+  fn test_type_name() {
+    let json = |input: &str| crate::Json::parse(input).unwrap();
 
-  // 去除首尾双引号
-  let chars = &quoted_input[1..quoted_input.len() - 1]
-    .chars()
-    .collect::<Vec<char>>();
-  let mut index = 0;
+    assert_eq!(json("\"a\"").type_name(), "string");
+    assert_eq!(json("1").type_name(), "number");
+    assert_eq!(json("true").type_name(), "boolean");
+    assert_eq!(json("null").type_name(), "null");
+    assert_eq!(json("[]").type_name(), "array");
 
-  while index < chars.len() {
-    let c = chars.get(index).unwrap();
-    index += 1;
+    let object = json("{\"a\": 1}");
+    assert_eq!(object.type_name(), "object");
+    let property = Ast::Property(object.as_object().unwrap().value[0].clone());
+    assert_eq!(property.type_name(), "property");
+    let key = Ast::Identifier(object.as_object().unwrap().value[0].key.clone());
+    assert_eq!(key.type_name(), "identifier");
+  }
 
-    match c {
-      '\\' => {
-        let next_c = chars.get(index).unwrap();
-        index += 1;
+  #[test]
+  fn test_walk() {
+    let mut json =
+      crate::Json::parse("{\"a\": [1, {\"b\": null}], \"c\": \"x\", \"d\": true}").unwrap();
 
-        match next_c {
-          'u' => {
-            // 解析 unicode 字符
-            let unicode =
-              u16::from_str_radix(&chars[index..index + 4].iter().collect::<String>(), 16).unwrap();
-            ret.push(char::from_u32(unicode as u32).unwrap());
-            index += 4;
-          }
-          '"' | '\\' | '/' => {
-            ret.push(next_c.clone());
-          }
-          'b' | 'f' | 'n' | 'r' | 't' => {
-            ret.push_str(ESCAPES.get(next_c).unwrap());
-          }
-          _ => return Err(format!("Unexpected escape character: {}", next_c)),
-        }
+    let names = json.walk().map(Ast::type_name).collect::<Vec<&str>>();
+    assert_eq!(
+      names,
+      ["object", "array", "number", "object", "null", "string", "boolean"]
+    );
+    assert_eq!(Ast::from(1.0).walk().count(), 1);
+
+    // 把所有数字翻倍，替换后的数组会继续被遍历
+    json.walk_mut(|ast| match ast {
+      Ast::Number(number) => number.value *= 2.0,
+      Ast::Null(_) => *ast = Ast::from(vec![Ast::from(3.0)]),
+      _ => {}
+    });
+    assert_eq!(
+      json.to_string().unwrap(),
+      "{\"a\":[2,{\"b\":[6]}],\"c\":\"x\",\"d\":true}"
+    );
+  }
+
+  #[test]
+  fn test_source_text() {
+    let source = "{\"名字\": \"caf\u{e9} \\u00e9\", \"n\": [1.50, -0]}";
+    let json = crate::Json::parse(source).unwrap();
+
+    assert_eq!(json.source_text(source), source);
+    assert_eq!(
+      json.pointer("/名字").unwrap().source_text(source),
+      "\"caf\u{e9} \\u00e9\""
+    );
+    assert_eq!(
+      json.pointer("/n").unwrap().source_text(source),
+      "[1.50, -0]"
+    );
+    assert_eq!(json.pointer("/n/0").unwrap().source_text(source), "1.50");
+    let key = &json.as_object().unwrap().value[0].key;
+    assert_eq!(key.span.slice(source), "\"名字\"");
+
+    // 手动构建的节点没有原文
+    assert_eq!(Ast::from(1.0).source_text(source), "");
+  }
+
+  #[test]
+  fn test_retain_recursive() {
+    let mut json = crate::json!({
+      "name": "a",
+      "empty": "",
+      "nested": {"x": null, "y": [{"z": null, "w": ""}, null, ""], "keep": 0},
+      "gone": null
+    });
+    let mut calls = 0;
+
+    json.retain_recursive(|_, value| {
+      calls += 1;
+      !value.is_null() && value.as_str() != Some("")
+    });
+    assert!(json.value_eq(&crate::json!({
+      "name": "a",
+      "nested": {"y": [{}, null, ""], "keep": 0}
+    })));
+    assert_eq!(calls, 9);
+
+    json.retain_items_recursive(|item| !item.is_null() && !item.is_empty());
+    assert!(json.value_eq(&crate::json!({"name": "a", "nested": {"y": [], "keep": 0}})));
+  }
+
+  #[test]
+  fn test_key_str() {
+    let object = parse_object("{\"a\": 1, \"\\u0062\\n\": 2, \"\": 3}");
+    let keys = object
+      .value
+      .iter()
+      .map(PropertyAst::key_str)
+      .collect::<Vec<&str>>();
+
+    assert_eq!(keys, ["a", "b\n", ""]);
+    assert_eq!(PropertyAst::new("k", Ast::from(())).key_str(), "k");
+  }
+
+  #[test]
+  fn test_take() {
+    let mut json = crate::Json::parse("{\"a\": [1, 2]}").unwrap();
+    let Ast::Object(object) = &mut json else {
+      panic!("expected an object");
+    };
+
+    let items = object.get_mut("a").unwrap().take();
+    assert_eq!(items.to_string().unwrap(), "[1,2]");
+    assert_eq!(json.to_string().unwrap(), "{\"a\":null}");
+
+    // 把取出的子树包一层再放回去
+    let Ast::Object(object) = &mut json else {
+      panic!("expected an object");
+    };
+    object.get_mut("a").unwrap().replace(Ast::from(vec![items]));
+    assert_eq!(json.to_string().unwrap(), "{\"a\":[[1,2]]}");
+  }
+
+  #[test]
+  fn test_by_span() {
+    use crate::visit::Visit;
+    use std::ops::ControlFlow;
+
+    // 字符串和数字分开收集，打乱了文档顺序
+    #[derive(Default)]
+    struct Collect {
+      strings: Vec<Ast>,
+      numbers: Vec<Ast>,
+    }
+
+    impl Visit for Collect {
+      fn visit_string(&mut self, ast: &mut StringAst) -> ControlFlow<()> {
+        self.strings.push(Ast::String(ast.clone()));
+        ControlFlow::Continue(())
+      }
+
+      fn visit_number(&mut self, ast: &mut NumberAst) -> ControlFlow<()> {
+        self.numbers.push(Ast::Number(ast.clone()));
+        ControlFlow::Continue(())
       }
-      _ => ret.push(c.clone()),
     }
+
+    let mut json = crate::Json::parse("[1, \"a\", {\"b\": 2}, \"c\", 3]").unwrap();
+    let mut collect = Collect::default();
+    let _ = collect.visit_json(&mut json);
+
+    let mut nodes: Vec<&Ast> = collect.strings.iter().chain(&collect.numbers).collect();
+    nodes.sort_by(|a, b| Ast::by_span(a, b));
+
+    let offsets: Vec<_> = nodes
+      .iter()
+      .map(|node| node.get_span().start.offset)
+      .collect();
+    assert_eq!(offsets, vec![1, 4, 10, 15, 19, 24]);
+    assert_eq!(nodes[2].as_str(), Some("b"));
+    assert_eq!(nodes[3].as_f64(), Some(2.0));
+  }
+
+  #[test]
+  fn test_cmp_value() {
+    let json = |input: &str| crate::Json::parse(input).unwrap();
+    let nan = Ast::from(f64::NAN);
+
+    assert_eq!(json("-0").cmp_value(&json("0")), Ordering::Equal);
+    assert_eq!(nan.cmp_value(&Ast::from(-f64::NAN)), Ordering::Equal);
+    assert_eq!(nan.cmp_value(&Ast::from(f64::INFINITY)), Ordering::Greater);
+    assert_eq!(json("[1, 2]").cmp_value(&json("[1]")), Ordering::Greater);
+    assert_eq!(
+      json("{\"a\": 1}").cmp_value(&json("{\"a\": 2}")),
+      Ordering::Less
+    );
+    // span 不参与比较
+    assert_eq!(json(" \"x\"").cmp_value(&Ast::from("x")), Ordering::Equal);
+  }
+
+  fn string_token(value: &str, line: usize, column: usize, offset: usize) -> (String, Span) {
+    let start = Loc {
+      line,
+      column,
+      offset,
+    };
+    (
+      value.to_string(),
+      Span {
+        end: start.clone(),
+        start,
+      },
+    )
+  }
+
+  #[test]
+  fn test_parse_string_error_span() {
+    let (value, span) = string_token("\"ab\\x\"", 3, 5, 20);
+    let err = parse_string(&value, &span, false).unwrap_err();
+
+    assert_eq!(err.message, "Unexpected escape character: x");
+    let err_span = err.span.unwrap();
+    assert_eq!(
+      (
+        err_span.start.line,
+        err_span.start.column,
+        err_span.start.offset
+      ),
+      (3, 8, 23)
+    );
+    assert_eq!(err_span.end.column, 10);
+  }
+
+  #[test]
+  fn test_parse_string_error_after_newline() {
+    let (value, span) = string_token("\"a\nb\\uD800\"", 1, 1, 0);
+    let err = parse_string(&value, &span, false).unwrap_err();
+
+    assert_eq!(err.message, "Invalid unicode escape: \\uD800");
+    let err_span = err.span.unwrap();
+    assert_eq!((err_span.start.line, err_span.start.column), (2, 2));
+    assert_eq!((err_span.end.line, err_span.end.column), (2, 8));
+  }
+
+  #[test]
+  fn test_parse_string_unterminated_escape() {
+    let (value, span) = string_token("\"a\\\"", 1, 1, 0);
+    let err = parse_string(&value, &span, false).unwrap_err();
+
+    assert_eq!(err.message, "Unterminated escape sequence");
+    assert_eq!(err.span.unwrap().start.column, 3);
+  }
+
+  #[test]
+  fn test_invalid_escape_reported_by_parse() {
+    // tokenizer 已经拦截非法转义，这里确认错误同样带有位置
+    let err = Json::parse("[\n  \"\\q\"]").unwrap_err();
+    let err_span = err.span.unwrap();
+
+    assert_eq!((err_span.start.line, err_span.start.column), (2, 5));
   }
 
-  Ok(ret)
+  #[test]
+  fn test_parse_string_short_unicode_escape() {
+    let (value, span) = string_token("\"\\u12\"", 1, 1, 0);
+    let err = parse_string(&value, &span, false).unwrap_err();
+
+    assert_eq!(err.message, "Incomplete unicode escape");
+    let err_span = err.span.unwrap();
+    assert_eq!((err_span.start.column, err_span.end.column), (2, 4));
+
+    let (value, span) = string_token("\"\\u+12a\"", 1, 1, 0);
+    let err = parse_string(&value, &span, false).unwrap_err();
+    assert_eq!(err.message, "Invalid unicode escape: \\u+12a");
+
+    let (value, span) = string_token("\"\\u0041\"", 1, 1, 0);
+    assert_eq!(parse_string(&value, &span, false).unwrap(), "A");
+  }
+
+  #[test]
+  fn test_number_as_decimal_str() {
+    let json = crate::Json::parse("[0.1, 12345678901234567890, 1.10, -0.0, 1E-7]").unwrap();
+    let Ast::Array(array) = &json else {
+      panic!("expected an array");
+    };
+    let decimals = array
+      .value
+      .iter()
+      .map(|item| match item.as_ref() {
+        Ast::Number(n) => n.as_decimal_str().into_owned(),
+        _ => panic!("expected a number"),
+      })
+      .collect::<Vec<String>>();
+
+    assert_eq!(
+      decimals,
+      ["0.1", "12345678901234567890", "1.10", "-0.0", "1E-7"]
+    );
+    // f64 已经丢失了精度
+    assert_ne!(array.value[1].as_f64().unwrap().to_string(), decimals[1]);
+
+    let Ast::Number(built) = Ast::from(0.5) else {
+      panic!("expected a number");
+    };
+    assert_eq!(built.as_decimal_str(), "0.5");
+  }
+
+  #[test]
+  fn test_number_is_integer() {
+    let is_integer = |input: &str| match Json::parse(input).unwrap() {
+      Ast::Number(n) => n.is_integer(),
+      _ => panic!("expected a number"),
+    };
+
+    assert!(is_integer("5"));
+    assert!(is_integer("-0"));
+    assert!(is_integer("12345678901234567890"));
+    assert!(!is_integer("5.0"));
+    assert!(!is_integer("5e0"));
+    assert!(!is_integer("5E2"));
+    assert!(!is_integer("-1.5"));
+
+    assert!(matches!(Ast::from(3i64), Ast::Number(n) if n.is_integer()));
+    assert!(matches!(Ast::from(3.0), Ast::Number(n) if n.is_integer()));
+    assert!(matches!(Ast::from(3.5), Ast::Number(n) if !n.is_integer()));
+    assert!(matches!(Ast::from(f64::NAN), Ast::Number(n) if !n.is_integer()));
+  }
 }
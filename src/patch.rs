@@ -0,0 +1,480 @@
+use std::{collections::HashMap, error::Error, fmt};
+
+use crate::{
+  parser::{Ast, ObjectAst},
+  pointer::{parse_index, parse_pointer, to_pointer, PathSegment},
+};
+
+// RFC 6902 JSON Patch 操作，path、from 为 JSON Pointer
+#[derive(Debug, PartialEq, Clone)]
+pub enum PatchOp {
+  Add { path: String, value: Ast },
+  Remove { path: String },
+  Replace { path: String, value: Ast },
+  Move { from: String, path: String },
+  Copy { from: String, path: String },
+  Test { path: String, value: Ast },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PatchError {
+  // 不是合法的 JSON Pointer，或把节点移动到自己的子节点中
+  InvalidPointer(String),
+  // 目标或其父节点不存在
+  NotFound(String),
+  TestFailed(String),
+}
+
+impl fmt::Display for PatchError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      PatchError::InvalidPointer(path) => write!(f, "Invalid JSON Pointer {:?}", path),
+      PatchError::NotFound(path) => write!(f, "Path {:?} does not exist", path),
+      PatchError::TestFailed(path) => write!(f, "Test failed at {:?}", path),
+    }
+  }
+}
+
+impl Error for PatchError {}
+
+impl Ast {
+  // 生成把 old 变成 new 的补丁，按顺序应用即可
+  pub fn diff(old: &Ast, new: &Ast) -> Vec<PatchOp> {
+    let mut ops = vec![];
+    diff_value(&mut vec![], old, new, &mut ops);
+    ops
+  }
+}
+
+impl Ast {
+  // 任意一步失败时整个补丁都不生效
+  pub fn apply_patch(&mut self, ops: &[PatchOp]) -> Result<(), PatchError> {
+    let mut doc = self.clone();
+
+    for op in ops {
+      match op {
+        PatchOp::Add { path, value } => add(&mut doc, path, value.clone())?,
+        PatchOp::Remove { path } => {
+          remove(&mut doc, path)?;
+        }
+        PatchOp::Replace { path, value } => {
+          *get_mut(&mut doc, path)? = value.clone();
+        }
+        PatchOp::Move { from, path } => {
+          if path.starts_with(&format!("{}/", from)) {
+            return Err(PatchError::InvalidPointer(path.clone()));
+          }
+          let value = remove(&mut doc, from)?;
+          add(&mut doc, path, value)?;
+        }
+        PatchOp::Copy { from, path } => {
+          let value = get_mut(&mut doc, from)?.clone();
+          add(&mut doc, path, value)?;
+        }
+        PatchOp::Test { path, value } => {
+          if !test_eq(get_mut(&mut doc, path)?, value) {
+            return Err(PatchError::TestFailed(path.clone()));
+          }
+        }
+      }
+    }
+
+    *self = doc;
+    Ok(())
+  }
+}
+
+// RFC 6902 4.6：对象按 key 比较成员，与顺序无关；数组仍按位置逐个比较
+fn test_eq(a: &Ast, b: &Ast) -> bool {
+  match (a, b) {
+    (Ast::Object(a), Ast::Object(b)) => {
+      let (a, b) = (members(a), members(b));
+      a.len() == b.len()
+        && a
+          .iter()
+          .all(|(key, a)| b.get(key).map_or(false, |b| test_eq(a, b)))
+    }
+    (Ast::Array(a), Ast::Array(b)) => {
+      a.value.len() == b.value.len()
+        && a
+          .value
+          .iter()
+          .zip(b.value.iter())
+          .all(|(a, b)| test_eq(a, b))
+    }
+    _ => a.value_eq(b),
+  }
+}
+
+// 有重复的 key 时取最后一个
+fn members(object: &ObjectAst) -> HashMap<&str, &Ast> {
+  object
+    .value
+    .iter()
+    .map(|property| (property.key.value.value.as_str(), property.value.as_ref()))
+    .collect()
+}
+
+fn segments(path: &str) -> Result<Vec<String>, PatchError> {
+  parse_pointer(path).ok_or_else(|| PatchError::InvalidPointer(path.to_string()))
+}
+
+fn get_mut<'a>(doc: &'a mut Ast, path: &str) -> Result<&'a mut Ast, PatchError> {
+  let segments = segments(path)?;
+  let segments = segments.iter().map(String::as_str).collect::<Vec<&str>>();
+
+  doc
+    .get_path_mut(&segments)
+    .ok_or_else(|| PatchError::NotFound(path.to_string()))
+}
+
+// 返回 path 的父节点和最后一段 key，path 为根节点时返回 None
+fn parent_mut<'a>(
+  doc: &'a mut Ast,
+  path: &str,
+) -> Result<Option<(&'a mut Ast, String)>, PatchError> {
+  let mut segments = segments(path)?;
+  let Some(last) = segments.pop() else {
+    return Ok(None);
+  };
+  let segments = segments.iter().map(String::as_str).collect::<Vec<&str>>();
+
+  match doc.get_path_mut(&segments) {
+    Some(parent) => Ok(Some((parent, last))),
+    None => Err(PatchError::NotFound(path.to_string())),
+  }
+}
+
+fn add(doc: &mut Ast, path: &str, value: Ast) -> Result<(), PatchError> {
+  let Some((parent, last)) = parent_mut(doc, path)? else {
+    *doc = value;
+    return Ok(());
+  };
+  let not_found = || PatchError::NotFound(path.to_string());
+
+  match parent {
+    Ast::Object(object) => {
+      object.insert(last, value);
+    }
+    Ast::Array(array) => {
+      // `-` 表示追加到末尾
+      let index = match last.as_str() {
+        "-" => array.value.len(),
+        last => parse_index(last)
+          .filter(|index| *index <= array.value.len())
+          .ok_or_else(not_found)?,
+      };
+      array.insert(index, value);
+    }
+    _ => return Err(not_found()),
+  }
+
+  Ok(())
+}
+
+fn remove(doc: &mut Ast, path: &str) -> Result<Ast, PatchError> {
+  let not_found = || PatchError::NotFound(path.to_string());
+  let Some((parent, last)) = parent_mut(doc, path)? else {
+    return Err(PatchError::InvalidPointer(path.to_string()));
+  };
+
+  match parent {
+    Ast::Object(object) => object.remove(&last).ok_or_else(not_found),
+    Ast::Array(array) => parse_index(&last)
+      .and_then(|index| array.remove(index))
+      .ok_or_else(not_found),
+    _ => Err(not_found()),
+  }
+}
+
+fn diff_value(path: &mut Vec<PathSegment>, old: &Ast, new: &Ast, ops: &mut Vec<PatchOp>) {
+  if old.value_eq(new) {
+    return;
+  }
+
+  match (old, new) {
+    (Ast::Object(old), Ast::Object(new)) => {
+      for property in &old.value {
+        let key = &property.key.value.value;
+        path.push(PathSegment::Key(key.clone()));
+
+        match new.get(key) {
+          Some(value) => diff_value(path, &property.value, value, ops),
+          None => ops.push(PatchOp::Remove {
+            path: to_pointer(path),
+          }),
+        }
+
+        path.pop();
+      }
+
+      for property in &new.value {
+        let key = &property.key.value.value;
+
+        if !old.contains_key(key) {
+          path.push(PathSegment::Key(key.clone()));
+          ops.push(PatchOp::Add {
+            path: to_pointer(path),
+            value: property.value.as_ref().clone(),
+          });
+          path.pop();
+        }
+      }
+    }
+    (Ast::Array(old), Ast::Array(new)) => {
+      for (index, (old, new)) in old.value.iter().zip(new.value.iter()).enumerate() {
+        path.push(PathSegment::Index(index));
+        diff_value(path, old, new, ops);
+        path.pop();
+      }
+
+      for (index, value) in new.value.iter().enumerate().skip(old.value.len()) {
+        path.push(PathSegment::Index(index));
+        ops.push(PatchOp::Add {
+          path: to_pointer(path),
+          value: value.as_ref().clone(),
+        });
+        path.pop();
+      }
+
+      // 从后往前删，前面的下标不受影响
+      for index in (new.value.len()..old.value.len()).rev() {
+        path.push(PathSegment::Index(index));
+        ops.push(PatchOp::Remove {
+          path: to_pointer(path),
+        });
+        path.pop();
+      }
+    }
+    _ => ops.push(PatchOp::Replace {
+      path: to_pointer(path),
+      value: new.clone(),
+    }),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{json, Json};
+
+  use super::*;
+
+  fn add(path: &str, value: Ast) -> PatchOp {
+    PatchOp::Add {
+      path: path.to_string(),
+      value,
+    }
+  }
+
+  fn remove(path: &str) -> PatchOp {
+    PatchOp::Remove {
+      path: path.to_string(),
+    }
+  }
+
+  fn replace(path: &str, value: Ast) -> PatchOp {
+    PatchOp::Replace {
+      path: path.to_string(),
+      value,
+    }
+  }
+
+  fn assert_ops(old: Ast, new: Ast, expected: Vec<PatchOp>) {
+    let ops = Ast::diff(&old, &new);
+
+    assert_eq!(ops.len(), expected.len(), "{:#?}", ops);
+    for (op, expected) in ops.iter().zip(expected.iter()) {
+      match (op, expected) {
+        (PatchOp::Add { path, value }, PatchOp::Add { path: p, value: v })
+        | (PatchOp::Replace { path, value }, PatchOp::Replace { path: p, value: v }) => {
+          assert_eq!(path, p);
+          assert!(value.value_eq(v), "{:#?}", op);
+        }
+        _ => assert_eq!(op, expected),
+      }
+    }
+  }
+
+  #[test]
+  fn test_diff_equal() {
+    let old = Json::parse("{\"a\": [1, {\"b\": null}]}").unwrap();
+    let new = json!({"a": [1, {"b": null}]});
+    assert_eq!(Ast::diff(&old, &new), vec![]);
+  }
+
+  #[test]
+  fn test_diff_object_keys() {
+    assert_ops(
+      json!({"a": 1, "b": 2}),
+      json!({"b": 3, "c/d": true}),
+      vec![
+        remove("/a"),
+        replace("/b", json!(3)),
+        add("/c~1d", json!(true)),
+      ],
+    );
+  }
+
+  #[test]
+  fn test_diff_nested() {
+    assert_ops(
+      json!({"a": {"b": [1, 2, 3], "c": "x"}}),
+      json!({"a": {"b": [1, 5], "c": {"d": null}}}),
+      vec![
+        replace("/a/b/1", json!(5)),
+        remove("/a/b/2"),
+        replace("/a/c", json!({"d": null})),
+      ],
+    );
+
+    assert_ops(
+      json!([1]),
+      json!([1, [2], 3]),
+      vec![add("/1", json!([2])), add("/2", json!(3))],
+    );
+    assert_ops(
+      json!([1, 2, 3]),
+      json!([]),
+      vec![remove("/2"), remove("/1"), remove("/0")],
+    );
+  }
+
+  #[test]
+  fn test_diff_type_change() {
+    assert_ops(json!({"a": [1]}), json!([1]), vec![replace("", json!([1]))]);
+    assert_ops(
+      json!({"a": "1"}),
+      json!({"a": 1}),
+      vec![replace("/a", json!(1))],
+    );
+  }
+
+  fn apply(doc: &mut Ast, ops: Vec<PatchOp>) -> Result<(), PatchError> {
+    doc.apply_patch(&ops)
+  }
+
+  #[test]
+  fn test_apply_add_remove_replace() {
+    let mut doc = json!({"a": [1, 2], "b": {"c": "x"}});
+    apply(
+      &mut doc,
+      vec![
+        add("/a/1", json!(5)),
+        add("/a/-", json!(9)),
+        add("/b/d", json!(null)),
+        add("/b/c", json!("y")),
+        remove("/a/0"),
+        replace("/b/d", json!([true])),
+      ],
+    )
+    .unwrap();
+    assert!(doc.value_eq(&json!({"a": [5, 2, 9], "b": {"c": "y", "d": [true]}})));
+
+    apply(&mut doc, vec![replace("", json!(1))]).unwrap();
+    assert!(doc.value_eq(&json!(1)));
+  }
+
+  #[test]
+  fn test_apply_move_copy() {
+    let mut doc = json!({"a": {"b": 1}, "list": [1, 2, 3]});
+    apply(
+      &mut doc,
+      vec![
+        PatchOp::Move {
+          from: "/a/b".to_string(),
+          path: "/c".to_string(),
+        },
+        PatchOp::Copy {
+          from: "/list".to_string(),
+          path: "/a/list".to_string(),
+        },
+        PatchOp::Move {
+          from: "/list/0".to_string(),
+          path: "/list/-".to_string(),
+        },
+      ],
+    )
+    .unwrap();
+    assert!(doc.value_eq(&json!({"a": {"list": [1, 2, 3]}, "list": [2, 3, 1], "c": 1})));
+
+    let err = apply(
+      &mut doc,
+      vec![PatchOp::Move {
+        from: "/a".to_string(),
+        path: "/a/list/x".to_string(),
+      }],
+    );
+    assert_eq!(
+      err,
+      Err(PatchError::InvalidPointer("/a/list/x".to_string()))
+    );
+  }
+
+  #[test]
+  fn test_apply_test_op() {
+    let mut doc = json!({"a": [1, "x"]});
+    let test = |path: &str, value| PatchOp::Test {
+      path: path.to_string(),
+      value,
+    };
+
+    apply(
+      &mut doc,
+      vec![test("/a/1", json!("x")), add("/b", json!(1))],
+    )
+    .unwrap();
+    assert!(doc.value_eq(&json!({"a": [1, "x"], "b": 1})));
+
+    // test 失败时之前的操作也不生效
+    let err = apply(
+      &mut doc,
+      vec![remove("/b"), test("/a", json!([1])), add("/c", json!(2))],
+    );
+    assert_eq!(err, Err(PatchError::TestFailed("/a".to_string())));
+    assert!(doc.value_eq(&json!({"a": [1, "x"], "b": 1})));
+
+    // 对象成员的顺序不影响 test，数组元素的顺序仍然影响
+    let mut doc = json!({"o": {"a": 1, "b": {"c": [1, 2], "d": null}}});
+    apply(
+      &mut doc,
+      vec![test("/o", json!({"b": {"d": null, "c": [1, 2]}, "a": 1}))],
+    )
+    .unwrap();
+    let err = apply(&mut doc, vec![test("/o/b/c", json!([2, 1]))]);
+    assert_eq!(err, Err(PatchError::TestFailed("/o/b/c".to_string())));
+    let err = apply(&mut doc, vec![test("/o", json!({"a": 1}))]);
+    assert_eq!(err, Err(PatchError::TestFailed("/o".to_string())));
+  }
+
+  #[test]
+  fn test_apply_errors() {
+    let mut doc = json!({"a": [1]});
+
+    assert_eq!(
+      apply(&mut doc, vec![add("/a/5", json!(1))]),
+      Err(PatchError::NotFound("/a/5".to_string()))
+    );
+    assert_eq!(
+      apply(&mut doc, vec![remove("/x")]),
+      Err(PatchError::NotFound("/x".to_string()))
+    );
+    assert_eq!(
+      apply(&mut doc, vec![replace("/x/y", json!(1))]),
+      Err(PatchError::NotFound("/x/y".to_string()))
+    );
+    assert_eq!(
+      apply(&mut doc, vec![add("a", json!(1))]),
+      Err(PatchError::InvalidPointer("a".to_string()))
+    );
+  }
+
+  #[test]
+  fn test_diff_then_apply() {
+    let old = json!({"a": {"b": [1, 2, 3], "c": "x"}, "d": null});
+    let new = json!({"a": {"b": [1, 5], "c": {"d": null}}, "e": [true]});
+
+    let mut doc = old.clone();
+    doc.apply_patch(&Ast::diff(&old, &new)).unwrap();
+    assert!(doc.value_eq(&new));
+  }
+}
@@ -0,0 +1,257 @@
+use std::fmt;
+
+use crate::parser::Ast;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PathSegment {
+  Key(String),
+  Index(usize),
+}
+
+impl fmt::Display for PathSegment {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      // RFC 6901：先转义 ~，再转义 /
+      PathSegment::Key(key) => write!(f, "{}", key.replace('~', "~0").replace('/', "~1")),
+      PathSegment::Index(index) => write!(f, "{}", index),
+    }
+  }
+}
+
+// 把路径格式化为 JSON Pointer，根节点为空字符串
+pub fn to_pointer(path: &[PathSegment]) -> String {
+  let mut pointer = String::new();
+
+  for segment in path {
+    pointer.push('/');
+    pointer.push_str(&segment.to_string());
+  }
+
+  pointer
+}
+
+// 把 JSON Pointer 拆成未转义的 key，必须为空或以 / 开头
+pub fn parse_pointer(pointer: &str) -> Option<Vec<String>> {
+  if pointer.is_empty() {
+    return Some(vec![]);
+  }
+
+  pointer.strip_prefix('/').map(|pointer| {
+    pointer
+      .split('/')
+      // 与转义的顺序相反：先还原 /，再还原 ~
+      .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+      .collect()
+  })
+}
+
+impl Ast {
+  // 对象按 key 查找，数组按下标查找，任意一步找不到或类型不匹配都返回 None
+  pub fn get_path(&self, path: &[&str]) -> Option<&Ast> {
+    path.iter().try_fold(self, |ast, segment| match ast {
+      Ast::Object(ast) => ast.get(segment),
+      Ast::Array(ast) => ast
+        .value
+        .get(parse_index(segment)?)
+        .map(|item| item.as_ref()),
+      _ => None,
+    })
+  }
+
+  // 空的对象和数组没有叶子节点
+  pub fn leaves(&self) -> impl Iterator<Item = (String, &Ast)> {
+    let mut leaves = vec![];
+    collect_leaves(self, &mut vec![], &mut leaves);
+    leaves.into_iter()
+  }
+
+  pub fn get_path_mut(&mut self, path: &[&str]) -> Option<&mut Ast> {
+    path.iter().try_fold(self, |ast, segment| match ast {
+      Ast::Object(ast) => ast.get_mut(segment),
+      Ast::Array(ast) => ast
+        .value
+        .get_mut(parse_index(segment)?)
+        .map(|item| item.as_mut()),
+      _ => None,
+    })
+  }
+
+  // 按 RFC 6901 JSON Pointer 查找，如 "/a~1b/0"，指针不合法时也返回 None
+  pub fn pointer(&self, pointer: &str) -> Option<&Ast> {
+    let path = parse_pointer(pointer)?;
+    self.get_path(&path.iter().map(String::as_str).collect::<Vec<&str>>())
+  }
+
+  pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Ast> {
+    let path = parse_pointer(pointer)?;
+    self.get_path_mut(&path.iter().map(String::as_str).collect::<Vec<&str>>())
+  }
+}
+
+// 按文档顺序收集叶子节点（字符串、数字、布尔、null）及其 JSON Pointer
+fn collect_leaves<'a>(
+  ast: &'a Ast,
+  path: &mut Vec<PathSegment>,
+  leaves: &mut Vec<(String, &'a Ast)>,
+) {
+  match ast {
+    Ast::Object(ast) => {
+      for property in &ast.value {
+        path.push(PathSegment::Key(property.key.value.value.clone()));
+        collect_leaves(&property.value, path, leaves);
+        path.pop();
+      }
+    }
+    Ast::Array(ast) => {
+      for (index, item) in ast.value.iter().enumerate() {
+        path.push(PathSegment::Index(index));
+        collect_leaves(item, path, leaves);
+        path.pop();
+      }
+    }
+    Ast::Property(_) | Ast::Identifier(_) => {}
+    _ => leaves.push((to_pointer(path), ast)),
+  }
+}
+
+// 数组下标只接受不带前导 0 的十进制数字
+pub(crate) fn parse_index(segment: &str) -> Option<usize> {
+  if segment.is_empty()
+    || !segment.bytes().all(|b| b.is_ascii_digit())
+    || (segment.len() > 1 && segment.starts_with('0'))
+  {
+    return None;
+  }
+
+  segment.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::json;
+
+  use super::*;
+
+  #[test]
+  fn test_to_pointer() {
+    assert_eq!(to_pointer(&[]), "");
+    assert_eq!(
+      to_pointer(&[
+        PathSegment::Key("a/b".to_string()),
+        PathSegment::Index(0),
+        PathSegment::Key("m~n".to_string()),
+      ]),
+      "/a~1b/0/m~0n"
+    );
+  }
+
+  #[test]
+  fn test_parse_pointer() {
+    assert_eq!(parse_pointer(""), Some(vec![]));
+    assert_eq!(parse_pointer("/"), Some(vec!["".to_string()]));
+    assert_eq!(
+      parse_pointer("/a~1b/0/m~0n/~01"),
+      Some(vec![
+        "a/b".to_string(),
+        "0".to_string(),
+        "m~n".to_string(),
+        "~1".to_string()
+      ])
+    );
+    assert_eq!(parse_pointer("a"), None);
+  }
+
+  #[test]
+  fn test_get_path() {
+    let json = json!({"a": [{"b": "x"}, 2], "0": {"c": null}});
+
+    assert!(json.get_path(&[]).unwrap().value_eq(&json));
+    assert!(json
+      .get_path(&["a", "0", "b"])
+      .unwrap()
+      .value_eq(&json!("x")));
+    assert!(json.get_path(&["a", "1"]).unwrap().value_eq(&json!(2)));
+    // 对象的 key 也可以是数字
+    assert!(json.get_path(&["0", "c"]).unwrap().value_eq(&json!(null)));
+
+    assert_eq!(json.get_path(&["a", "2"]), None);
+    assert_eq!(json.get_path(&["a", "01"]), None);
+    assert_eq!(json.get_path(&["a", "+1"]), None);
+    assert_eq!(json.get_path(&["a", "b"]), None);
+    assert_eq!(json.get_path(&["a", "1", "x"]), None);
+    assert_eq!(json.get_path(&["missing"]), None);
+  }
+
+  #[test]
+  fn test_get_path_mut() {
+    let mut json = json!({"a": [{"b": "x"}]});
+
+    *json.get_path_mut(&["a", "0", "b"]).unwrap() = json!([true]);
+    assert!(json.value_eq(&json!({"a": [{"b": [true]}]})));
+    assert_eq!(json.get_path_mut(&["a", "0", "b", "1"]), None);
+  }
+
+  #[test]
+  fn test_pointer_mut() {
+    let mut json = json!({
+      "user": {"name": "a", "ssn": "123-45-6789"},
+      "a/b": {"m~n": [1, 2]}
+    });
+
+    *json.pointer_mut("/user/ssn").unwrap() = json!("***");
+    *json.pointer_mut("/a~1b/m~0n/1").unwrap() = json!(null);
+
+    assert_eq!(
+      json.to_string().unwrap(),
+      "{\"user\":{\"name\":\"a\",\"ssn\":\"***\"},\"a/b\":{\"m~n\":[1,null]}}"
+    );
+    assert_eq!(
+      json.pointer("/a~1b/m~0n/0").and_then(Ast::as_f64),
+      Some(1.0)
+    );
+    assert_eq!(json.pointer("").unwrap().as_object().unwrap().len(), 2);
+
+    for pointer in [
+      "/user/missing",
+      "/a~1b/m~0n/2",
+      "/a~1b/m~0n/01",
+      "/user/name/x",
+      "user",
+      "/a/b",
+    ] {
+      assert_eq!(json.pointer_mut(pointer), None, "{}", pointer);
+    }
+  }
+
+  #[test]
+  fn test_leaves() {
+    let json = json!({
+      "name": "app",
+      "db": {"host": "localhost", "ports": [5432, 5433]},
+      "a/b": [true, null, {"c~": []}],
+      "empty": {}
+    });
+    let leaves = json
+      .leaves()
+      .map(|(path, value)| (path, value.to_string().unwrap()))
+      .collect::<Vec<(String, String)>>();
+
+    assert_eq!(
+      leaves,
+      [
+        ("/name", "\"app\""),
+        ("/db/host", "\"localhost\""),
+        ("/db/ports/0", "5432"),
+        ("/db/ports/1", "5433"),
+        ("/a~1b/0", "true"),
+        ("/a~1b/1", "null"),
+      ]
+      .map(|(path, value)| (path.to_string(), value.to_string()))
+    );
+
+    let json = json!(1);
+    let leaves = json.leaves().collect::<Vec<(String, &Ast)>>();
+    assert_eq!(leaves.len(), 1);
+    assert_eq!(leaves[0].0, "");
+  }
+}
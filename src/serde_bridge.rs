@@ -0,0 +1,192 @@
+use std::{error::Error, fmt};
+
+use serde_core::de::DeserializeOwned;
+use serde_json::{Map, Number, Value};
+
+use crate::parser::{Ast, BoolAst, NullAst, NumberAst};
+use crate::span::Span;
+use crate::{Json, ParseError};
+
+// Json::parse_into 的错误：文本不是合法的 JSON，或者结构与目标类型不匹配
+#[derive(Debug)]
+pub enum ParseIntoError {
+  Parse(ParseError),
+  Deserialize(serde_json::Error),
+}
+
+impl fmt::Display for ParseIntoError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ParseIntoError::Parse(error) => write!(f, "{}", error),
+      ParseIntoError::Deserialize(error) => write!(f, "{}", error),
+    }
+  }
+}
+
+impl Error for ParseIntoError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      ParseIntoError::Parse(error) => Some(error),
+      ParseIntoError::Deserialize(error) => Some(error),
+    }
+  }
+}
+
+impl Json {
+  // 解析后经过 to_serde 反序列化成 T，规则与 to_serde 相同
+  pub fn parse_into<T: DeserializeOwned>(input: &str) -> Result<T, ParseIntoError> {
+    let json = Json::parse(input).map_err(ParseIntoError::Parse)?;
+    serde_json::from_value(json.to_serde()).map_err(ParseIntoError::Deserialize)
+  }
+}
+
+impl Ast {
+  // 所有节点使用默认 span，数字保留 serde_json 的原始写法
+  pub fn from_serde(value: &Value) -> Ast {
+    match value {
+      Value::Null => Ast::Null(NullAst {
+        span: Span::default(),
+      }),
+      Value::Bool(value) => Ast::Boolean(BoolAst {
+        value: *value,
+        span: Span::default(),
+      }),
+      Value::Number(value) => Ast::Number(NumberAst {
+        value: value.as_f64().unwrap_or(f64::NAN),
+        raw: Some(value.to_string()),
+        span: Span::default(),
+      }),
+      Value::String(value) => Ast::from(value.as_str()),
+      Value::Array(items) => items.iter().map(Ast::from_serde).collect(),
+      Value::Object(map) => map
+        .iter()
+        .map(|(key, value)| (key.clone(), Ast::from_serde(value)))
+        .collect(),
+    }
+  }
+
+  // 丢弃 span；NaN、Infinity 在 serde_json 中无法表示，转成 null；
+  // 重复的 key 以最后一个为准，与 ObjectAst::as_map 一致
+  pub fn to_serde(&self) -> Value {
+    match self {
+      Ast::String(ast) => Value::String(ast.value.clone()),
+      Ast::Number(ast) => number_to_serde(ast),
+      Ast::Boolean(ast) => Value::Bool(ast.value),
+      Ast::Null(_) => Value::Null,
+      Ast::Object(ast) => Value::Object(
+        ast
+          .value
+          .iter()
+          .map(|property| (property.key.value.value.clone(), property.value.to_serde()))
+          .collect::<Map<_, _>>(),
+      ),
+      Ast::Property(ast) => ast.value.to_serde(),
+      Ast::Identifier(ast) => Value::String(ast.value.value.clone()),
+      Ast::Array(ast) => Value::Array(ast.value.iter().map(|item| item.to_serde()).collect()),
+    }
+  }
+}
+
+fn number_to_serde(ast: &NumberAst) -> Value {
+  // 整数优先按原始写法转换，超出 f64 精度的大整数不会丢失；-0 走 f64 以保留符号
+  if ast.is_integer() && !(ast.value == 0.0 && ast.value.is_sign_negative()) {
+    let raw = ast.as_decimal_str();
+    if let Ok(value) = raw.parse::<i64>() {
+      return Value::Number(value.into());
+    }
+    if let Ok(value) = raw.parse::<u64>() {
+      return Value::Number(value.into());
+    }
+  }
+
+  Number::from_f64(ast.value)
+    .map(Value::Number)
+    .unwrap_or(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use serde_core::{de, Deserialize, Deserializer};
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn test_from_serde() {
+    let value = json!({
+      "a": [1, -2, 1.5, null, true],
+      "b": { "c": "d", "e": {} },
+      "big": u64::MAX,
+    });
+    let ast = Ast::from_serde(&value);
+
+    assert!(ast.value_eq(
+      &crate::Json::parse(
+        "{\"a\": [1, -2, 1.5, null, true], \"b\": {\"c\": \"d\", \"e\": {}}, \"big\": 18446744073709551615}"
+      )
+      .unwrap()
+    ));
+    assert!(ast.get_span().is_synthetic());
+    assert_eq!(ast.to_serde(), value);
+  }
+
+  #[test]
+  fn test_to_serde() {
+    let ast =
+      crate::Json::parse("{\"n\": [0, -0, 1e2, 2.5, 9007199254740993], \"o\": {\"x\": [\"y\"]}}")
+        .unwrap();
+    let value = ast.to_serde();
+
+    assert_eq!(
+      value,
+      json!({
+        "n": [0, -0.0, 100.0, 2.5, 9007199254740993u64],
+        "o": { "x": ["y"] },
+      })
+    );
+    assert!(Ast::from_serde(&value).value_eq(&ast));
+    assert_eq!(Ast::from(f64::NAN).to_serde(), Value::Null);
+  }
+
+  #[derive(Debug, PartialEq)]
+  struct Config {
+    name: String,
+    count: u32,
+  }
+
+  // 没有 serde_derive，手写一个等价的实现
+  impl<'de> Deserialize<'de> for Config {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+      let mut map = HashMap::<String, Value>::deserialize(deserializer)?;
+      let mut field = |name: &'static str| map.remove(name).ok_or(de::Error::missing_field(name));
+
+      Ok(Config {
+        name: String::deserialize(field("name")?).map_err(de::Error::custom)?,
+        count: u32::deserialize(field("count")?).map_err(de::Error::custom)?,
+      })
+    }
+  }
+
+  #[test]
+  fn test_parse_into() {
+    let config = crate::Json::parse_into::<Config>("{\"name\": \"app\", \"count\": 3}").unwrap();
+    assert_eq!(
+      config,
+      Config {
+        name: "app".to_string(),
+        count: 3
+      }
+    );
+
+    let err = crate::Json::parse_into::<Config>("{\"name\": \"app\",").unwrap_err();
+    assert!(matches!(err, ParseIntoError::Parse(_)));
+
+    let err = crate::Json::parse_into::<Config>("{\"name\": \"app\", \"count\": -1}").unwrap_err();
+    assert!(matches!(err, ParseIntoError::Deserialize(_)));
+    let err = crate::Json::parse_into::<Config>("{\"count\": 1}").unwrap_err();
+    assert_eq!(err.to_string(), "missing field `name`");
+    assert!(err.source().is_some());
+  }
+}
@@ -0,0 +1,1288 @@
+use std::{collections::HashMap, error::Error, fmt, io, ops::ControlFlow, ops::RangeInclusive};
+
+use crate::{
+  escape::escape_into,
+  options::ParseOptions,
+  parser::{
+    ArrayAst, Ast, BoolAst, IdentifierAst, NullAst, NumberAst, ObjectAst, PropertyAst, StringAst,
+  },
+  span::{Loc, Span},
+  tokenizer::{Token, Tokenizer},
+  visit::Visit,
+  Json,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SerializeError {
+  NonFiniteNumber(f64),
+}
+
+impl fmt::Display for SerializeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      SerializeError::NonFiniteNumber(value) => {
+        write!(f, "Cannot serialize non-finite number {}", value)
+      }
+    }
+  }
+}
+
+impl Error for SerializeError {}
+
+impl From<SerializeError> for io::Error {
+  fn from(error: SerializeError) -> Self {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+  }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SerializeOptions {
+  // 把 NaN、Infinity、-Infinity 按 JSON5 字面量输出，否则报错
+  pub allow_non_finite: bool,
+  pub number_format: NumberFormat,
+  pub escape_policy: EscapePolicy,
+  // 输出时按 key 排序（稳定排序），不修改 Ast 中属性的顺序
+  pub sort_keys: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum EscapePolicy {
+  // 只转义引号、反斜杠和控制字符，其余按 UTF-8 原样输出
+  #[default]
+  Minimal,
+  // 所有非 ASCII 字符都转义成 \uXXXX
+  AsciiOnly,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct NumberFormat {
+  // 有原始写法时原样输出，不经过 f64，保证无损
+  pub preserve_raw: bool,
+  // 不是整数的数字（包括 `1.0`、`1e2` 这样的写法）总是带小数点，如 1.0、1.0e100
+  pub always_decimal_point: bool,
+  // 科学计数法的指数在这个范围内时用定点表示，否则用科学计数法，如 1e100、1e-7
+  pub fixed_exponents: RangeInclusive<i32>,
+}
+
+// 默认是最短的往返表示，定点与科学计数法的分界和 JavaScript 一致
+impl Default for NumberFormat {
+  fn default() -> Self {
+    Self {
+      preserve_raw: false,
+      always_decimal_point: false,
+      fixed_exponents: -6..=20,
+    }
+  }
+}
+
+impl Json {
+  pub fn to_string(&self) -> Result<String, SerializeError> {
+    self.to_string_with_options(&SerializeOptions::default())
+  }
+
+  pub fn to_string_with_options(
+    &self,
+    options: &SerializeOptions,
+  ) -> Result<String, SerializeError> {
+    let mut serializer = Serializer::new(options);
+    serializer.write_value(self)?;
+
+    Ok(serializer.output)
+  }
+
+  // RFC 8785 (JCS)：key 按 UTF-16 排序，数字按 ECMAScript 规则输出
+  pub fn to_canonical_string(&self) -> Result<String, SerializeError> {
+    let options = SerializeOptions::default();
+    let mut serializer = Serializer {
+      canonical: true,
+      ..Serializer::new(&options)
+    };
+    serializer.write_value(self)?;
+
+    Ok(serializer.output)
+  }
+
+  // 每两个空格缩进一层，空的对象、数组仍输出为 {}、[]
+  pub fn to_string_pretty(&self) -> Result<String, SerializeError> {
+    let options = SerializeOptions::default();
+    let mut serializer = Serializer {
+      indent: Some(0),
+      ..Serializer::new(&options)
+    };
+    serializer.write_value(self)?;
+
+    Ok(serializer.output)
+  }
+
+  // 与 to_string 输出相同，但边生成边写入 writer，不在内存中保留完整的输出
+  pub fn to_writer(&self, writer: &mut impl io::Write) -> io::Result<()> {
+    self.write_to(writer, None)
+  }
+
+  // 与 to_string_pretty 输出相同
+  pub fn to_writer_pretty(&self, writer: &mut impl io::Write) -> io::Result<()> {
+    self.write_to(writer, Some(0))
+  }
+
+  fn write_to(&self, writer: &mut impl io::Write, indent: Option<usize>) -> io::Result<()> {
+    let options = SerializeOptions::default();
+    let mut serializer = Serializer {
+      sink: Some(writer),
+      indent,
+      ..Serializer::new(&options)
+    };
+    serializer.write_value(self)?;
+    serializer.flush(true);
+
+    match serializer.io_error {
+      Some(error) => Err(error),
+      None => Ok(()),
+    }
+  }
+
+  // 只重新输出改动过的部分，其余文本（包括空白、注释）从 original 原样复制。
+  // 按 span 在 original 中找到对应节点，值相同就视为未改动；original 无法解析时整体重新输出
+  pub fn format_incremental(original: &str, ast: &Ast) -> Result<String, SerializeError> {
+    let source = Json::parse_relaxed(original).ok();
+    let mut nodes = HashMap::new();
+    for node in source.iter().flat_map(|source| source.walk()) {
+      let span = node.get_span();
+      nodes.insert((span.start.offset, span.end.offset), node);
+    }
+
+    let options = SerializeOptions::default();
+    let mut incremental = Incremental {
+      serializer: Serializer::new(&options),
+      original,
+      byte_offsets: original
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([original.len()])
+        .collect(),
+      nodes,
+    };
+    // 顶层值前后的空白、注释也保留
+    let root = source.as_ref().map(|source| source.get_span().clone());
+    if let Some(root) = &root {
+      incremental.copy(0, root.start.offset);
+    }
+    incremental.write(ast)?;
+    if let Some(root) = &root {
+      incremental.copy(root.end.offset, incremental.byte_offsets.len() - 1);
+    }
+
+    Ok(incremental.serializer.output)
+  }
+}
+
+// to_writer 时输出积累到这么多字节就写入 writer
+const CHUNK_SIZE: usize = 8 * 1024;
+
+struct Serializer<'a> {
+  output: String,
+  options: &'a SerializeOptions,
+  canonical: bool,
+  // 美化输出时当前的缩进层级，None 表示紧凑格式
+  indent: Option<usize>,
+  // 有 sink 时 output 只是缓冲区，写入出错后丢弃剩余的输出，最后返回第一个错误
+  sink: Option<&'a mut dyn io::Write>,
+  io_error: Option<io::Error>,
+}
+
+impl<'a> Serializer<'a> {
+  fn new(options: &'a SerializeOptions) -> Self {
+    Self {
+      output: String::new(),
+      options,
+      canonical: false,
+      indent: None,
+      sink: None,
+      io_error: None,
+    }
+  }
+
+  fn flush(&mut self, force: bool) {
+    let Some(sink) = &mut self.sink else {
+      return;
+    };
+    if !force && self.output.len() < CHUNK_SIZE {
+      return;
+    }
+
+    if self.io_error.is_none() {
+      if let Err(error) = sink.write_all(self.output.as_bytes()) {
+        self.io_error = Some(error);
+      }
+    }
+    self.output.clear();
+  }
+
+  // 美化输出时换行并缩进到 depth 层
+  fn newline(&mut self, depth: usize) {
+    if self.indent.is_some() {
+      self.output.push('\n');
+      for _ in 0..depth {
+        self.output.push_str("  ");
+      }
+    }
+  }
+
+  fn write_value(&mut self, ast: &Ast) -> Result<(), SerializeError> {
+    match ast {
+      Ast::String(ast) => self.write_string(&ast.value),
+      Ast::Number(ast) => self.write_number(ast)?,
+      Ast::Boolean(ast) => self
+        .output
+        .push_str(if ast.value { "true" } else { "false" }),
+      Ast::Null(_) => self.output.push_str("null"),
+      Ast::Object(ast) => {
+        let mut properties = ast.value.iter().collect::<Vec<&PropertyAst>>();
+        if self.canonical {
+          properties.sort_by(|a, b| {
+            let a = a.key.value.value.encode_utf16();
+            a.cmp(b.key.value.value.encode_utf16())
+          });
+        } else if self.options.sort_keys {
+          properties = ast.entries_sorted();
+        }
+
+        let depth = self.indent.unwrap_or_default();
+        self.output.push('{');
+        for (i, property) in properties.iter().enumerate() {
+          if i > 0 {
+            self.output.push(',');
+          }
+          self.newline(depth + 1);
+          self.indent = self.indent.map(|_| depth + 1);
+          self.write_property(property)?;
+          self.indent = self.indent.map(|_| depth);
+          self.flush(false);
+        }
+        if !properties.is_empty() {
+          self.newline(depth);
+        }
+        self.output.push('}');
+      }
+      Ast::Property(ast) => self.write_property(ast)?,
+      Ast::Identifier(ast) => self.write_string(&ast.value.value),
+      Ast::Array(ast) => {
+        let depth = self.indent.unwrap_or_default();
+        self.output.push('[');
+        for (i, item) in ast.value.iter().enumerate() {
+          if i > 0 {
+            self.output.push(',');
+          }
+          self.newline(depth + 1);
+          self.indent = self.indent.map(|_| depth + 1);
+          self.write_value(item)?;
+          self.indent = self.indent.map(|_| depth);
+          self.flush(false);
+        }
+        if !ast.is_empty() {
+          self.newline(depth);
+        }
+        self.output.push(']');
+      }
+    }
+
+    Ok(())
+  }
+
+  fn write_number(&mut self, ast: &NumberAst) -> Result<(), SerializeError> {
+    let value = ast.value;
+    let format = &self.options.number_format;
+
+    if value.is_finite() && self.canonical {
+      self.output.push_str(&es_number(value));
+    } else if value.is_finite() {
+      let mut number = match &ast.raw {
+        Some(raw) if format.preserve_raw => raw.clone(),
+        _ => format_number(value, &format.fixed_exponents, ""),
+      };
+
+      if format.always_decimal_point && !ast.is_integer() && !number.contains('.') {
+        let end = number.find(['e', 'E']).unwrap_or(number.len());
+        number.insert_str(end, ".0");
+      }
+      self.output.push_str(&number);
+    } else if !self.options.allow_non_finite {
+      return Err(SerializeError::NonFiniteNumber(value));
+    } else if value.is_nan() {
+      self.output.push_str("NaN");
+    } else if value > 0.0 {
+      self.output.push_str("Infinity");
+    } else {
+      self.output.push_str("-Infinity");
+    }
+
+    Ok(())
+  }
+
+  fn write_property(&mut self, ast: &PropertyAst) -> Result<(), SerializeError> {
+    self.write_string(&ast.key.value.value);
+    self.output.push(':');
+    if self.indent.is_some() {
+      self.output.push(' ');
+    }
+    self.write_value(&ast.value)
+  }
+
+  fn write_string(&mut self, value: &str) {
+    self.output.push('"');
+    let ascii_only = self.options.escape_policy == EscapePolicy::AsciiOnly;
+    escape_into(&mut self.output, value, ascii_only);
+    self.output.push('"');
+  }
+}
+
+// 按紧凑格式重新输出，并给每个节点写入对应输出文本中的 span
+pub fn reindex_spans(ast: &mut Ast) -> Result<String, SerializeError> {
+  let options = SerializeOptions::default();
+  let mut reindexer = Reindexer {
+    serializer: Serializer::new(&options),
+    loc: Loc {
+      line: 1,
+      column: 1,
+      offset: 0,
+    },
+    error: None,
+  };
+  let _ = reindexer.visit_json(ast);
+
+  match reindexer.error {
+    Some(error) => Err(error),
+    None => Ok(reindexer.serializer.output),
+  }
+}
+
+struct Reindexer<'a> {
+  serializer: Serializer<'a>,
+  // 当前输出末尾的位置，紧凑格式没有换行
+  loc: Loc,
+  error: Option<SerializeError>,
+}
+
+impl<'a> Reindexer<'a> {
+  // 把 serializer 新写入的内容计入位置，返回这段内容的 span
+  fn advance(&mut self, written_from: usize) -> Span {
+    let len = self.serializer.output[written_from..].chars().count();
+    let start = self.loc.clone();
+    self.loc.column += len;
+    self.loc.offset += len;
+
+    Span {
+      start,
+      end: self.loc.clone(),
+    }
+  }
+
+  fn push(&mut self, c: char) {
+    self.serializer.output.push(c);
+    self.loc.column += 1;
+    self.loc.offset += 1;
+  }
+
+  fn write(&mut self, f: impl FnOnce(&mut Serializer<'a>) -> Result<(), SerializeError>) -> Span {
+    let written_from = self.serializer.output.len();
+    if let Err(error) = f(&mut self.serializer) {
+      self.error = Some(error);
+    }
+
+    self.advance(written_from)
+  }
+
+  fn span_from(&self, start: Loc) -> Span {
+    Span {
+      start,
+      end: self.loc.clone(),
+    }
+  }
+
+  fn check(&self) -> ControlFlow<()> {
+    match self.error {
+      Some(_) => ControlFlow::Break(()),
+      None => ControlFlow::Continue(()),
+    }
+  }
+}
+
+impl<'a> Visit for Reindexer<'a> {
+  fn visit_string(&mut self, ast: &mut StringAst) -> ControlFlow<()> {
+    ast.span = self.write(|serializer| {
+      serializer.write_string(&ast.value);
+      Ok(())
+    });
+    ControlFlow::Continue(())
+  }
+
+  fn visit_number(&mut self, ast: &mut NumberAst) -> ControlFlow<()> {
+    ast.span = self.write(|serializer| serializer.write_number(ast));
+    self.check()
+  }
+
+  fn visit_boolean(&mut self, ast: &mut BoolAst) -> ControlFlow<()> {
+    ast.span = self.write(|serializer| serializer.write_value(&Ast::from(ast.value)));
+    ControlFlow::Continue(())
+  }
+
+  fn visit_null(&mut self, ast: &mut NullAst) -> ControlFlow<()> {
+    ast.span = self.write(|serializer| serializer.write_value(&Ast::from(())));
+    ControlFlow::Continue(())
+  }
+
+  fn visit_object(&mut self, ast: &mut ObjectAst) -> ControlFlow<()> {
+    let start = self.loc.clone();
+
+    self.push('{');
+    for (i, property) in ast.value.iter_mut().enumerate() {
+      if i > 0 {
+        self.push(',');
+      }
+      self.visit_property(property)?;
+    }
+    self.push('}');
+
+    ast.span = self.span_from(start);
+    ControlFlow::Continue(())
+  }
+
+  fn visit_property(&mut self, ast: &mut PropertyAst) -> ControlFlow<()> {
+    let start = self.loc.clone();
+
+    self.visit_identifier(&mut ast.key)?;
+    self.push(':');
+    self.visit_property_value(&ast.key.value.value, &mut ast.value)?;
+
+    ast.span = self.span_from(start);
+    ControlFlow::Continue(())
+  }
+
+  fn visit_identifier(&mut self, ast: &mut IdentifierAst) -> ControlFlow<()> {
+    self.visit_string(&mut ast.value)?;
+    ast.span = ast.value.span.clone();
+    ControlFlow::Continue(())
+  }
+
+  fn visit_array(&mut self, ast: &mut ArrayAst) -> ControlFlow<()> {
+    let start = self.loc.clone();
+
+    self.push('[');
+    for (i, item) in ast.value.iter_mut().enumerate() {
+      if i > 0 {
+        self.push(',');
+      }
+      self.visit_array_item(i, item)?;
+    }
+    self.push(']');
+
+    ast.span = self.span_from(start);
+    ControlFlow::Continue(())
+  }
+}
+
+struct Incremental<'a> {
+  serializer: Serializer<'a>,
+  original: &'a str,
+  // span 的 offset 按字符计算，这里是每个字符的字节位置，末尾多一个 original.len()
+  byte_offsets: Vec<usize>,
+  // original 中每个节点的 (start.offset, end.offset) -> 节点
+  nodes: HashMap<(usize, usize), &'a Ast>,
+}
+
+impl<'a> Incremental<'a> {
+  fn text(&self, start: usize, end: usize) -> &'a str {
+    &self.original[self.byte_offsets[start]..self.byte_offsets[end]]
+  }
+
+  fn copy(&mut self, start: usize, end: usize) {
+    let text = self.text(start, end);
+    self.serializer.output.push_str(text);
+  }
+
+  fn copy_span(&mut self, span: &Span) {
+    self.copy(span.start.offset, span.end.offset);
+  }
+
+  fn original_node(&self, ast: &Ast) -> Option<&'a Ast> {
+    let span = ast.get_span();
+    if span.is_synthetic() {
+      return None;
+    }
+    self
+      .nodes
+      .get(&(span.start.offset, span.end.offset))
+      .copied()
+  }
+
+  fn write(&mut self, ast: &Ast) -> Result<(), SerializeError> {
+    let original = self.original_node(ast);
+
+    match (ast, original) {
+      (_, Some(original)) if original.value_eq(ast) => self.copy_span(original.get_span()),
+      // 保留下来的元素连同前后的注释从原文复制，改动过的 key 和值重新输出
+      (Ast::Object(ast), Some(Ast::Object(original))) => {
+        let ranges = original
+          .value
+          .iter()
+          .map(|property| {
+            let end = property.value.get_span().end.offset;
+            (property.key.span.start.offset, end)
+          })
+          .collect::<Vec<(usize, usize)>>();
+        let survivors = survivors(&ranges, ast.value.iter().map(|property| &property.key.span));
+
+        if in_place(&survivors, ranges.len()) {
+          let mut offset = original.span.start.offset;
+          for (property, original_property) in ast.value.iter().zip(&original.value) {
+            self.copy(offset, original_property.key.span.start.offset);
+            self.write_property(property, Some(original_property), "")?;
+            offset = original_property.value.get_span().end.offset;
+          }
+          self.copy(offset, original.span.end.offset);
+        } else {
+          // 新增的 property 沿用第一个 property 的冒号写法
+          let colon = original.value.first().map_or(":", |property| {
+            let value_start = property.value.get_span().start.offset;
+            self.text(property.key.span.end.offset, value_start)
+          });
+          self.splice(&original.span, &ranges, &survivors, |this, i, j| {
+            let original_property = j.map(|j| &original.value[j]);
+            this.write_property(&ast.value[i], original_property, colon)
+          })?;
+        }
+      }
+      (Ast::Array(ast), Some(Ast::Array(original))) => {
+        let ranges = original
+          .value
+          .iter()
+          .map(|item| (item.get_span().start.offset, item.get_span().end.offset))
+          .collect::<Vec<(usize, usize)>>();
+        let survivors = survivors(&ranges, ast.value.iter().map(|item| item.get_span()));
+
+        if in_place(&survivors, ranges.len()) {
+          let mut offset = original.span.start.offset;
+          for (item, original_item) in ast.value.iter().zip(&original.value) {
+            let span = original_item.get_span();
+            self.copy(offset, span.start.offset);
+            self.write(item)?;
+            offset = span.end.offset;
+          }
+          self.copy(offset, original.span.end.offset);
+        } else {
+          self.splice(&original.span, &ranges, &survivors, |this, i, _| {
+            this.write(&ast.value[i])
+          })?;
+        }
+      }
+      // 新的容器按紧凑格式输出，其中来自原文的子节点仍然原样复制
+      (Ast::Object(ast), _) => {
+        self.serializer.output.push('{');
+        for (i, property) in ast.value.iter().enumerate() {
+          if i > 0 {
+            self.serializer.output.push(',');
+          }
+          self.write_property(property, None, ":")?;
+        }
+        self.serializer.output.push('}');
+      }
+      (Ast::Array(ast), _) => {
+        self.serializer.output.push('[');
+        for (i, item) in ast.value.iter().enumerate() {
+          if i > 0 {
+            self.serializer.output.push(',');
+          }
+          self.write(item)?;
+        }
+        self.serializer.output.push(']');
+      }
+      _ => self.serializer.write_value(ast)?,
+    }
+
+    Ok(())
+  }
+
+  // 有 original 时复制原来的 key（改名时重新输出）和 key 到值之间的文本，否则输出 key 和 colon
+  fn write_property(
+    &mut self,
+    property: &PropertyAst,
+    original: Option<&PropertyAst>,
+    colon: &str,
+  ) -> Result<(), SerializeError> {
+    match original {
+      Some(original) => {
+        let key_span = &original.key.span;
+        if property.key.value.value == original.key.value.value {
+          self.copy_span(key_span);
+        } else {
+          self.serializer.write_string(&property.key.value.value);
+        }
+        self.copy(key_span.end.offset, original.value.get_span().start.offset);
+      }
+      None => {
+        self.serializer.write_string(&property.key.value.value);
+        self.serializer.output.push_str(colon);
+      }
+    }
+
+    self.write(&property.value)
+  }
+
+  // ranges 是原容器中每个元素的 (start, end)，survivors[i] 是当前第 i 个元素对应的原元素下标。
+  // 元素之后到逗号、以及逗号之后同一行的文本（如行尾注释）属于该元素，下一行开始到下一个元素之间的
+  // 文本（缩进、注释）属于下一个元素，删除元素时一起去掉；新增的元素沿用原来的缩进
+  fn splice(
+    &mut self,
+    span: &Span,
+    ranges: &[(usize, usize)],
+    survivors: &[Option<usize>],
+    mut write_item: impl FnMut(&mut Self, usize, Option<usize>) -> Result<(), SerializeError>,
+  ) -> Result<(), SerializeError> {
+    let open = span.start.offset + 1;
+    let close = span.end.offset - 1;
+    // 每个元素后面的逗号（最后一个元素后面是尾逗号），以及同一行文本的结束位置
+    let after = ranges
+      .iter()
+      .enumerate()
+      .map(|(j, range)| {
+        let next = ranges.get(j + 1).map_or(close, |next| next.0);
+        let comma = self.find_comma(range.1, next);
+        let line_start = comma.map_or(range.1, |comma| comma + 1);
+        (comma, self.line_end(line_start, next))
+      })
+      .collect::<Vec<(Option<usize>, usize)>>();
+    let leading = |j: usize| match j {
+      0 => (open, ranges[0].0),
+      _ => (after[j - 1].1, ranges[j].0),
+    };
+
+    let indent = match ranges.len() {
+      0 => "",
+      1 => self.text(open, ranges[0].0),
+      _ => self.text(leading(1).0, ranges[1].0),
+    };
+    // 有换行时取最后一行开头的缩进，否则取末尾的空白
+    let indent = match indent.rfind('\n') {
+      Some(newline) => {
+        let line = &indent[newline + 1..];
+        &indent[newline..indent.len() - line.trim_start().len()]
+      }
+      None => &indent[indent.trim_end().len()..],
+    };
+
+    self.copy(span.start.offset, open);
+    // 上一个元素逗号之后的同一行文本，要等输出逗号之后再复制
+    let mut pending = None;
+    for (i, survivor) in survivors.iter().enumerate() {
+      if i > 0 {
+        self.serializer.output.push(',');
+      }
+      if let Some((start, end)) = pending.take() {
+        self.copy(start, end);
+      }
+
+      match *survivor {
+        Some(j) => {
+          let (start, end) = leading(j);
+          self.copy(start, end);
+          write_item(self, i, Some(j))?;
+
+          let (comma, line_end) = after[j];
+          if let Some(comma) = comma {
+            self.copy(ranges[j].1, comma);
+            pending = Some((comma + 1, line_end));
+          } else {
+            pending = Some((ranges[j].1, line_end));
+          }
+        }
+        None => {
+          self.serializer.output.push_str(indent);
+          write_item(self, i, None)?;
+        }
+      }
+    }
+
+    // 原来有尾逗号时保留
+    if let (Some(_), Some((Some(_), _))) = (survivors.last(), after.last()) {
+      self.serializer.output.push(',');
+    }
+    if let Some((start, end)) = pending {
+      self.copy(start, end);
+    }
+    let tail = after.last().map_or(open, |after| after.1);
+    self.copy(tail, span.end.offset);
+
+    Ok(())
+  }
+
+  // 从 start 开始到换行符（不含）或 end 的位置
+  fn line_end(&self, start: usize, end: usize) -> usize {
+    let text = self.text(start, end);
+    match text.find('\n') {
+      Some(newline) => start + text[..newline].chars().count(),
+      None => start,
+    }
+  }
+
+  // 两个元素之间的文本中逗号的位置，跳过注释
+  fn find_comma(&self, start: usize, end: usize) -> Option<usize> {
+    let options = ParseOptions {
+      allow_comments: true,
+      ..ParseOptions::default()
+    };
+    match Tokenizer::with_options(self.text(start, end), options).next_token() {
+      Ok(Some(Token::Comma(token))) => Some(start + token.span.start.offset),
+      _ => None,
+    }
+  }
+}
+
+// 元素个数没变，并且没有元素移动位置，改动过的元素按位置一一替换，保留原来的分隔符和注释
+fn in_place(survivors: &[Option<usize>], len: usize) -> bool {
+  survivors.len() == len
+    && survivors
+      .iter()
+      .enumerate()
+      .all(|(i, j)| j.map_or(true, |j| i == j))
+}
+
+// 按 span 的起始位置找到仍在原位的元素，顺序必须与原文一致，否则当作新元素输出
+fn survivors<'s>(
+  ranges: &[(usize, usize)],
+  spans: impl Iterator<Item = &'s Span>,
+) -> Vec<Option<usize>> {
+  let mut next = 0;
+
+  spans
+    .map(|span| {
+      if span.is_synthetic() {
+        return None;
+      }
+      let j = ranges
+        .binary_search_by_key(&span.start.offset, |range| range.0)
+        .ok()
+        .filter(|j| *j >= next)?;
+      next = j + 1;
+      Some(j)
+    })
+    .collect()
+}
+
+// ECMAScript Number.prototype.toString，JCS 要求的数字格式
+fn es_number(value: f64) -> String {
+  if value == 0.0 {
+    // -0 也输出 0
+    return "0".to_string();
+  }
+
+  format_number(value, &(-6..=20), "+")
+}
+
+// 最短的往返表示，科学计数法的指数在 fixed 范围内时改用定点表示，
+// exp_plus 是正指数前面的符号，JCS 要求写成 e+21
+fn format_number(value: f64, fixed: &RangeInclusive<i32>, exp_plus: &str) -> String {
+  // {:e} 给出最短的往返表示，如 1.2345e-7
+  let sci = format!("{:e}", value.abs());
+  let (mantissa, exp) = sci.split_once('e').unwrap();
+  let digits = mantissa.replace('.', "");
+  let k = digits.len() as i32;
+  let exp = exp.parse::<i32>().unwrap();
+  // 小数点位于第 n 位之后
+  let n = exp + 1;
+
+  let body = if !fixed.contains(&exp) {
+    let sign = if exp >= 0 { exp_plus } else { "-" };
+
+    if k == 1 {
+      format!("{}e{}{}", digits, sign, exp.abs())
+    } else {
+      format!("{}.{}e{}{}", &digits[..1], &digits[1..], sign, exp.abs())
+    }
+  } else if k <= n {
+    format!("{}{}", digits, "0".repeat((n - k) as usize))
+  } else if 0 < n {
+    format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+  } else {
+    format!("0.{}{}", "0".repeat(-n as usize), digits)
+  };
+
+  if value.is_sign_negative() {
+    format!("-{}", body)
+  } else {
+    body
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_round_trip() {
+    let input = "{\"a\":[1,-2.5,true,false,null],\"b\":{\"c\":\"x\\\"y\\\\z\\n\"}}";
+
+    assert_eq!(Json::parse(input).unwrap().to_string().unwrap(), input);
+  }
+
+  #[test]
+  fn test_non_finite() {
+    assert_eq!(
+      Json::from(f64::NAN).to_string().map_err(|e| e.to_string()),
+      Err("Cannot serialize non-finite number NaN".to_string())
+    );
+  }
+
+  #[test]
+  fn test_non_finite_literals() {
+    let options = SerializeOptions {
+      allow_non_finite: true,
+      ..SerializeOptions::default()
+    };
+    let json = Json::from(vec![
+      Json::from(f64::NAN),
+      Json::from(f64::INFINITY),
+      Json::from(f64::NEG_INFINITY),
+    ]);
+
+    assert_eq!(
+      json.to_string_with_options(&options).unwrap(),
+      "[NaN,Infinity,-Infinity]"
+    );
+    assert!(json.to_string().is_err());
+  }
+
+  #[test]
+  fn test_number_format_default() {
+    let json = Json::parse(
+      "[0, -0, 1, 1.0, -2.50, 1e3, 123456789012345678901234, 1e100, 1.5e-300, 0.000001, 1e-7, \
+       0.1, 9007199254740993]",
+    )
+    .unwrap();
+
+    assert_eq!(
+      json.to_string().unwrap(),
+      "[0,-0,1,1,-2.5,1000,1.2345678901234569e23,1e100,1.5e-300,0.000001,1e-7,0.1,9007199254740992]"
+    );
+    // 默认输出可以被重新解析回相同的值
+    let output = json.to_string().unwrap();
+    assert!(Json::parse(&output).unwrap().value_eq(&json));
+    assert_eq!(
+      Json::from(123e18).to_string().unwrap(),
+      "123000000000000000000"
+    );
+  }
+
+  #[test]
+  fn test_negative_zero() {
+    let json = Json::parse("[-0, 0, -0.0, 0.0, -0e5]").unwrap();
+    let signs = json
+      .as_array()
+      .unwrap()
+      .value
+      .iter()
+      .map(|item| item.as_f64().unwrap().is_sign_negative())
+      .collect::<Vec<bool>>();
+    assert_eq!(signs, [true, false, true, false, true]);
+
+    assert_eq!(json.to_string().unwrap(), "[-0,0,-0,0,-0]");
+    let preserve_raw = SerializeOptions {
+      number_format: NumberFormat {
+        preserve_raw: true,
+        ..NumberFormat::default()
+      },
+      ..SerializeOptions::default()
+    };
+    assert_eq!(
+      json.to_string_with_options(&preserve_raw).unwrap(),
+      "[-0,0,-0.0,0.0,-0e5]"
+    );
+    assert_eq!(Json::from(-0.0).to_string().unwrap(), "-0");
+    // JCS 要求 -0 输出为 0
+    assert_eq!(json.to_canonical_string().unwrap(), "[0,0,0,0,0]");
+  }
+
+  #[test]
+  fn test_number_format_options() {
+    let json = Json::parse("[1, 1.0, 2.50, 1e2, 1E100, -3e-10, 9007199254740993]").unwrap();
+    let to_string = |number_format| {
+      json
+        .to_string_with_options(&SerializeOptions {
+          number_format,
+          ..SerializeOptions::default()
+        })
+        .unwrap()
+    };
+
+    assert_eq!(
+      to_string(NumberFormat {
+        preserve_raw: true,
+        ..NumberFormat::default()
+      }),
+      "[1,1.0,2.50,1e2,1E100,-3e-10,9007199254740993]"
+    );
+    assert_eq!(
+      to_string(NumberFormat {
+        always_decimal_point: true,
+        ..NumberFormat::default()
+      }),
+      "[1,1.0,2.5,100.0,1.0e100,-3.0e-10,9007199254740992]"
+    );
+    assert_eq!(
+      to_string(NumberFormat {
+        fixed_exponents: -2..=2,
+        ..NumberFormat::default()
+      }),
+      "[1,1,2.5,100,1e100,-3e-10,9.007199254740992e15]"
+    );
+    assert_eq!(
+      Json::from(1e100)
+        .to_string_with_options(&SerializeOptions {
+          number_format: NumberFormat {
+            fixed_exponents: 0..=100,
+            ..NumberFormat::default()
+          },
+          ..SerializeOptions::default()
+        })
+        .unwrap(),
+      format!("1{}", "0".repeat(100))
+    );
+  }
+
+  #[test]
+  fn test_sort_keys() {
+    let json =
+      Json::parse("{\"b\": 1, \"a\": {\"z\": [{\"y\": 1, \"x\": 2}], \"c\": 3}, \"a\": 0}")
+        .unwrap();
+    let options = SerializeOptions {
+      sort_keys: true,
+      ..SerializeOptions::default()
+    };
+
+    assert_eq!(
+      json.to_string().unwrap(),
+      "{\"b\":1,\"a\":{\"z\":[{\"y\":1,\"x\":2}],\"c\":3},\"a\":0}"
+    );
+    // 重复的 key 保持原有顺序
+    assert_eq!(
+      json.to_string_with_options(&options).unwrap(),
+      "{\"a\":{\"c\":3,\"z\":[{\"x\":2,\"y\":1}]},\"a\":0,\"b\":1}"
+    );
+    // Ast 本身没有被修改
+    let keys = json
+      .as_object()
+      .unwrap()
+      .value
+      .iter()
+      .map(|property| property.key.value.value.as_str())
+      .collect::<Vec<&str>>();
+    assert_eq!(keys, ["b", "a", "a"]);
+  }
+
+  #[test]
+  fn test_to_string_pretty() {
+    let json =
+      Json::parse("{\"a\": [1, {\"b\": null}], \"c\": {}, \"d\": [], \"e\": \"x\"}").unwrap();
+
+    assert_eq!(
+      json.to_string_pretty().unwrap(),
+      "{\n  \"a\": [\n    1,\n    {\n      \"b\": null\n    }\n  ],\n  \"c\": {},\n  \"d\": [],\n  \"e\": \"x\"\n}"
+    );
+    assert_eq!(Json::from(1.0).to_string_pretty().unwrap(), "1");
+    assert!(Json::parse(&json.to_string_pretty().unwrap())
+      .unwrap()
+      .value_eq(&json));
+  }
+
+  #[test]
+  fn test_to_writer() {
+    let json = Json::parse("{\"a\": [1, 2.5, \"é\\n\"], \"b\": {\"c\": null}}").unwrap();
+
+    let mut output = vec![];
+    json.to_writer(&mut output).unwrap();
+    assert_eq!(
+      String::from_utf8(output).unwrap(),
+      json.to_string().unwrap()
+    );
+
+    let mut output = vec![];
+    json.to_writer_pretty(&mut output).unwrap();
+    assert_eq!(
+      String::from_utf8(output).unwrap(),
+      json.to_string_pretty().unwrap()
+    );
+
+    // 超过一个缓冲区大小的输出分多次写入
+    let big = (0..5000).map(|i| Json::from(i as i64)).collect::<Json>();
+    let mut output = vec![];
+    big.to_writer(&mut output).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), big.to_string().unwrap());
+
+    let err = Json::from(f64::NAN).to_writer(&mut vec![]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    struct Full;
+
+    impl io::Write for Full {
+      fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::Other, "full"))
+      }
+
+      fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+      }
+    }
+
+    let err = big.to_writer(&mut Full).unwrap_err();
+    assert_eq!(err.to_string(), "full");
+  }
+
+  #[test]
+  fn test_escape_policy() {
+    let json = Json::from("caf\u{e9} \u{1f600}\n");
+
+    assert_eq!(json.to_string().unwrap(), "\"caf\u{e9} \u{1f600}\\n\"");
+
+    let output = json
+      .to_string_with_options(&SerializeOptions {
+        escape_policy: EscapePolicy::AsciiOnly,
+        ..SerializeOptions::default()
+      })
+      .unwrap();
+    assert_eq!(output, r#""caf\u00e9 \ud83d\ude00\n""#);
+    assert!(output.is_ascii());
+    assert!(Json::parse(&output).unwrap().value_eq(&json));
+  }
+
+  #[test]
+  fn test_format_incremental() {
+    let original = "{\n  // 版本\n  \"version\": 1,\n  \"tags\": [ \"a\",  \"b\" ],\n  \"nested\": {\"x\": 1.50}\n}\n";
+    let mut json = Json::parse_relaxed(original).unwrap();
+
+    // 未改动时与原文完全一致
+    assert_eq!(Json::format_incremental(original, &json).unwrap(), original);
+
+    *json.pointer_mut("/version").unwrap() = Json::from(2i64);
+    // 原地修改的值保留原来的 span，也会被重新输出
+    if let Some(Ast::String(tag)) = json.pointer_mut("/tags/1") {
+      tag.value.push('!');
+    }
+    assert_eq!(
+      Json::format_incremental(original, &json).unwrap(),
+      "{\n  // 版本\n  \"version\": 2,\n  \"tags\": [ \"a\",  \"b!\" ],\n  \"nested\": {\"x\": 1.50}\n}\n"
+    );
+
+    // 增加元素时其余元素保留原文，新元素沿用原来的缩进和冒号写法
+    let object = match &mut json {
+      Ast::Object(object) => object,
+      _ => unreachable!(),
+    };
+    object.insert("extra", Json::from(()));
+    object.value[0].key.set_value("v");
+    assert_eq!(
+      Json::format_incremental(original, &json).unwrap(),
+      "{\n  // 版本\n  \"v\": 2,\n  \"tags\": [ \"a\",  \"b!\" ],\n  \"nested\": {\"x\": 1.50},\n  \"extra\": null\n}\n"
+    );
+  }
+
+  #[test]
+  fn test_format_incremental_splice() {
+    let original = "{\n  // a\n  \"a\": 1, // one\n  /* b */ \"b\": [1, /* x */ 2,\n    3],\n  \"c\": {} // last\n}";
+    let mut json = Json::parse_relaxed(original).unwrap();
+    let format = |json: &Json| Json::format_incremental(original, json).unwrap();
+    fn object(json: &mut Json) -> &mut ObjectAst {
+      match json {
+        Ast::Object(object) => object,
+        _ => unreachable!(),
+      }
+    }
+
+    // 在有注释的对象开头插入 key
+    object(&mut json)
+      .value
+      .insert(0, PropertyAst::new("z", Json::from(0i64)));
+    assert_eq!(
+      format(&json),
+      "{\n  \"z\": 0,\n  // a\n  \"a\": 1, // one\n  /* b */ \"b\": [1, /* x */ 2,\n    3],\n  \"c\": {} // last\n}"
+    );
+
+    // 删除元素时连同它前面的注释一起删除
+    object(&mut json).value.remove(0);
+    object(&mut json).remove("a");
+    assert_eq!(
+      format(&json),
+      "{\n  /* b */ \"b\": [1, /* x */ 2,\n    3],\n  \"c\": {} // last\n}"
+    );
+
+    if let Some(Ast::Array(array)) = json.pointer_mut("/b") {
+      array.remove(0);
+      array.push(Json::from(4i64));
+    }
+    if let Some(Ast::Object(object)) = json.pointer_mut("/c") {
+      object.insert("d", Json::from(true));
+    }
+    assert_eq!(
+      format(&json),
+      "{\n  /* b */ \"b\": [ /* x */ 2,\n    3, 4],\n  \"c\": {\"d\":true} // last\n}"
+    );
+
+    // 元素全部删除时去掉尾逗号
+    let original = "[1, 2, /* end */]";
+    let mut json = Json::parse_relaxed(original).unwrap();
+    if let Ast::Array(array) = &mut json {
+      array.value.clear();
+    }
+    assert_eq!(
+      Json::format_incremental(original, &json).unwrap(),
+      "[ /* end */]"
+    );
+  }
+
+  #[test]
+  fn test_format_incremental_rename_key() {
+    let original = "{ \"a\" : [1,\n 2] }";
+    let mut json = Json::parse(original).unwrap();
+    if let Ast::Object(object) = &mut json {
+      object.value[0].key.set_value("b\"c");
+    }
+
+    assert_eq!(
+      Json::format_incremental(original, &json).unwrap(),
+      "{ \"b\\\"c\" : [1,\n 2] }"
+    );
+    // 无法解析的原文不复制任何内容
+    assert_eq!(
+      Json::format_incremental("{", &json).unwrap(),
+      "{\"b\\\"c\":[1,2]}"
+    );
+  }
+
+  #[test]
+  fn test_canonical() {
+    // RFC 8785 3.2.2
+    let input = r#"{
+      "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+      "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+      "literals": [null, true, false]
+    }"#;
+
+    assert_eq!(
+      Json::parse(input).unwrap().to_canonical_string().unwrap(),
+      r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+    );
+  }
+
+  #[test]
+  fn test_canonical_key_order() {
+    // RFC 8785 3.2.3，按 UTF-16 排序时 emoji 排在 U+FB33 之前
+    let json = crate::json!({
+      "\u{20ac}": "Euro Sign",
+      "\r": "Carriage Return",
+      "\u{fb33}": "Hebrew Letter Dalet With Dagesh",
+      "1": "One",
+      "\u{1f600}": "Emoji: Grinning Face",
+      "\u{80}": "Control",
+      "\u{f6}": "Latin Small Letter O With Diaeresis"
+    });
+
+    assert_eq!(
+      json.to_canonical_string().unwrap(),
+      "{\"\\r\":\"Carriage Return\",\"1\":\"One\",\"\u{80}\":\"Control\",\
+       \"\u{f6}\":\"Latin Small Letter O With Diaeresis\",\"\u{20ac}\":\"Euro Sign\",\
+       \"\u{1f600}\":\"Emoji: Grinning Face\",\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}"
+    );
+  }
+
+  #[test]
+  fn test_canonical_numbers() {
+    for (value, expected) in [
+      (0.0, "0"),
+      (-0.0, "0"),
+      (1.0, "1"),
+      (-1.5, "-1.5"),
+      (1e20, "100000000000000000000"),
+      (1e21, "1e+21"),
+      (123e18, "123000000000000000000"),
+      (1.5e21, "1.5e+21"),
+      (0.000001, "0.000001"),
+      (1e-7, "1e-7"),
+      (-1.25e-7, "-1.25e-7"),
+      (9007199254740992.0, "9007199254740992"),
+      (5e-324, "5e-324"),
+      (f64::MAX, "1.7976931348623157e+308"),
+      (0.1 + 0.2, "0.30000000000000004"),
+    ] {
+      assert_eq!(es_number(value), expected);
+    }
+
+    assert!(Json::from(f64::NAN).to_canonical_string().is_err());
+  }
+
+  // 检查每个节点的 span 截取出的文本重新解析后与节点的值相同
+  fn assert_spans(ast: &Ast, chars: &[char]) {
+    let span = ast.get_span();
+    let text = chars[span.start.offset..span.end.offset]
+      .iter()
+      .collect::<String>();
+    assert_eq!(span.start.column, span.start.offset + 1);
+
+    match ast {
+      Ast::Object(object) => {
+        assert!(Json::parse(&text).unwrap().value_eq(ast));
+        for property in &object.value {
+          let key = &property.key.span;
+          let key_text = chars[key.start.offset..key.end.offset]
+            .iter()
+            .collect::<String>();
+          assert_eq!(
+            Json::parse(&key_text).unwrap().as_str(),
+            Some(property.key.value.value.as_str())
+          );
+          assert_eq!(property.span.start, property.key.span.start);
+          assert_eq!(property.span.end, property.value.get_span().end);
+          assert_spans(&property.value, chars);
+        }
+      }
+      Ast::Array(array) => {
+        assert!(Json::parse(&text).unwrap().value_eq(ast));
+        for item in &array.value {
+          assert_spans(item, chars);
+        }
+      }
+      _ => assert!(Json::parse(&text).unwrap().value_eq(ast)),
+    }
+  }
+
+  #[test]
+  fn test_reindex_spans() {
+    let mut json = Json::parse("{\n  \"a\": [1, \"wörld\"],\n  \"b\": {\"c\": null}\n}").unwrap();
+
+    let Json::Object(object) = &mut json else {
+      panic!("expected an object");
+    };
+    object.insert("新", crate::json!({"x": [true, -1.5]}));
+    object.remove("a");
+    let Some(Json::Object(b)) = object.get_mut("b") else {
+      panic!("expected an object");
+    };
+    b.insert("d", Json::from("\"q\""));
+
+    let output = reindex_spans(&mut json).unwrap();
+    assert_eq!(
+      output,
+      "{\"b\":{\"c\":null,\"d\":\"\\\"q\\\"\"},\"新\":{\"x\":[true,-1.5]}}"
+    );
+    assert!(!json.get_span().is_synthetic());
+    assert_eq!(json.get_span().end.offset, output.chars().count());
+
+    let chars = output.chars().collect::<Vec<char>>();
+    assert_spans(&json, &chars);
+  }
+
+  #[test]
+  fn test_reindex_spans_error() {
+    let mut json = crate::json!([1, (f64::NAN)]);
+    assert!(matches!(
+      reindex_spans(&mut json),
+      Err(SerializeError::NonFiniteNumber(value)) if value.is_nan()
+    ));
+  }
+}
@@ -1,3 +1,7 @@
+use std::cmp::Ordering;
+
+use crate::error::{chars_before, line_starts, skip_bom};
+
 #[derive(Debug, PartialEq, Clone, Default)]
 pub struct Loc {
   pub line: usize,
@@ -10,3 +14,229 @@ pub struct Span {
   pub start: Loc,
   pub end: Loc,
 }
+
+// 按文档顺序比较：先比 start.offset，再比 end.offset。
+// offset 相同但行列不同的 span 不可比较
+impl PartialOrd for Span {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    match self.cmp_offset(other) {
+      Ordering::Equal if self != other => None,
+      ordering => Some(ordering),
+    }
+  }
+}
+
+impl Span {
+  // 以字符为单位的偏移，区间左闭右开
+  pub fn contains(&self, offset: usize) -> bool {
+    self.start.offset <= offset && offset < self.end.offset
+  }
+
+  pub fn len(&self) -> usize {
+    self.end.offset - self.start.offset
+  }
+
+  // 与 len 相同，名字上明确是字符数
+  pub fn char_len(&self) -> usize {
+    self.len()
+  }
+
+  // 在 source 中占的字节数，source 必须是产生这个 span 的输入
+  pub fn byte_len(&self, source: &str) -> usize {
+    self.slice(source).len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  // 解析出来的位置行号从 1 开始，全零的 span 只会来自手动构建的节点
+  pub fn is_synthetic(&self) -> bool {
+    *self == Span::default()
+  }
+
+  // 截取 span 覆盖的原文。offset 按字符计算，需要先换算成字节位置，超出原文的部分截断
+  pub fn slice<'a>(&self, source: &'a str) -> &'a str {
+    let byte_pos = |offset: usize| {
+      source
+        .char_indices()
+        .nth(offset)
+        .map_or(source.len(), |(i, _)| i)
+    };
+    let start = byte_pos(self.start.offset);
+    let end = byte_pos(self.end.offset).max(start);
+
+    &source[start..end]
+  }
+
+  // 只按 offset 比较的全序，可以直接用于 sort_by
+  pub fn cmp_offset(&self, other: &Span) -> Ordering {
+    self
+      .start
+      .offset
+      .cmp(&other.start.offset)
+      .then(self.end.offset.cmp(&other.end.offset))
+  }
+
+  // 转成 LSP 的 ((line, character), (line, character))：从 0 开始，列按 UTF-16 code unit 计算
+  pub fn to_lsp_range(&self, source: &str) -> ((u32, u32), (u32, u32)) {
+    let starts = line_starts(source);
+
+    (
+      lsp_position(source, &starts, &self.start),
+      lsp_position(source, &starts, &self.end),
+    )
+  }
+
+  pub fn merge(&self, other: &Span) -> Span {
+    let start = if self.start.offset <= other.start.offset {
+      &self.start
+    } else {
+      &other.start
+    };
+    let end = if self.end.offset >= other.end.offset {
+      &self.end
+    } else {
+      &other.end
+    };
+
+    Span {
+      start: start.clone(),
+      end: end.clone(),
+    }
+  }
+}
+
+fn lsp_position(source: &str, starts: &[usize], loc: &Loc) -> (u32, u32) {
+  let line = loc.line.saturating_sub(1);
+  let line_start = starts.get(line).copied().unwrap_or(source.len());
+  // Tokenizer 跳过 BOM 时列号不计入 BOM
+  let line_start = skip_bom(source, loc.line, line_start);
+
+  let character = source[line_start..]
+    .chars()
+    .take(chars_before(source, line_start, loc))
+    .map(char::len_utf16)
+    .sum::<usize>();
+
+  (line as u32, character as u32)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn span(start: usize, end: usize) -> Span {
+    Span {
+      start: Loc {
+        line: 1,
+        column: start + 1,
+        offset: start,
+      },
+      end: Loc {
+        line: 1,
+        column: end + 1,
+        offset: end,
+      },
+    }
+  }
+
+  #[test]
+  fn test_contains() {
+    let a = span(2, 5);
+
+    assert!(!a.contains(1));
+    assert!(a.contains(2));
+    assert!(a.contains(4));
+    assert!(!a.contains(5));
+    assert_eq!(a.len(), 3);
+    assert!(span(3, 3).is_empty());
+  }
+
+  #[test]
+  fn test_merge() {
+    // 相邻
+    assert_eq!(span(0, 2).merge(&span(2, 4)), span(0, 4));
+    // 嵌套
+    assert_eq!(span(0, 10).merge(&span(3, 4)), span(0, 10));
+    assert_eq!(span(3, 4).merge(&span(0, 10)), span(0, 10));
+    // 不相交
+    assert_eq!(span(6, 8).merge(&span(1, 2)), span(1, 8));
+  }
+
+  #[test]
+  fn test_partial_ord() {
+    assert!(span(0, 2) < span(1, 2));
+    assert!(span(1, 2) < span(1, 3));
+    assert!(span(3, 4) > span(1, 10));
+    assert_eq!(span(1, 2).partial_cmp(&span(1, 2)), Some(Ordering::Equal));
+
+    let mut other_line = span(1, 2);
+    other_line.start.line = 2;
+    assert_eq!(span(1, 2).partial_cmp(&other_line), None);
+    assert_eq!(span(1, 2).cmp_offset(&other_line), Ordering::Equal);
+  }
+
+  #[test]
+  fn test_to_lsp_range() {
+    let source = "{\"😀\": [\"a\"],\r\n \"𝄞x\": \"é\"}";
+    let json = crate::Json::parse(source).unwrap();
+    let object = json.as_object().unwrap();
+
+    // 😀 占两个 UTF-16 code unit
+    assert_eq!(
+      object.value[0].value.get_span().to_lsp_range(source),
+      ((0, 7), (0, 12))
+    );
+    assert_eq!(
+      object.value[1].key.span.to_lsp_range(source),
+      ((1, 1), (1, 6))
+    );
+    assert_eq!(
+      object.value[1].value.get_span().to_lsp_range(source),
+      ((1, 8), (1, 11))
+    );
+    assert_eq!(json.get_span().to_lsp_range(source), ((0, 0), (1, 12)));
+
+    let source = "\u{FEFF}[1]";
+    let json = crate::Json::parse(source).unwrap();
+    assert_eq!(json.get_span().to_lsp_range(source), ((0, 0), (0, 3)));
+  }
+
+  #[test]
+  fn test_char_and_byte_len() {
+    let source = "{\"ascii\": \"abc\", \"日本\": \"é😀\"}";
+    let json = crate::Json::parse(source).unwrap();
+
+    let ascii = json.pointer("/ascii").unwrap().get_span();
+    assert_eq!((ascii.char_len(), ascii.byte_len(source)), (5, 5));
+
+    let unicode = json.pointer("/日本").unwrap().get_span();
+    assert_eq!(unicode.slice(source), "\"é😀\"");
+    assert_eq!((unicode.char_len(), unicode.byte_len(source)), (4, 8));
+
+    let key = &json.as_object().unwrap().value[1].key.span;
+    assert_eq!((key.char_len(), key.byte_len(source)), (4, 8));
+
+    let root = json.get_span();
+    assert_eq!(root.char_len(), source.chars().count());
+    assert_eq!(root.byte_len(source), source.len());
+  }
+
+  #[test]
+  fn test_is_synthetic() {
+    let mut json = crate::Json::parse("{\"a\": 1}").unwrap();
+    assert!(!json.get_span().is_synthetic());
+    assert!(!crate::Json::parse("1").unwrap().get_span().is_synthetic());
+
+    let crate::Json::Object(object) = &mut json else {
+      panic!("expected an object");
+    };
+    object.insert("b", crate::Json::from(true));
+    assert!(!object.get("a").unwrap().get_span().is_synthetic());
+    assert!(object.get("b").unwrap().get_span().is_synthetic());
+    assert!(object.value[1].span.is_synthetic());
+
+    assert_eq!(json.to_string().unwrap(), "{\"a\":1,\"b\":true}");
+  }
+}
@@ -0,0 +1,118 @@
+use crate::parser::Ast;
+
+// Ast::stats 的结果，node_count、depth 也由它得出
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Stats {
+  pub strings: usize,
+  pub numbers: usize,
+  pub booleans: usize,
+  pub nulls: usize,
+  pub objects: usize,
+  pub arrays: usize,
+  pub total_nodes: usize,
+  pub max_depth: usize,
+}
+
+impl Ast {
+  // 一次遍历统计各类节点的个数和最大深度，用显式的栈，不受嵌套深度限制
+  pub fn stats(&self) -> Stats {
+    let mut stats = Stats::default();
+    let mut stack = vec![(self, 1)];
+
+    while let Some((ast, depth)) = stack.pop() {
+      stats.max_depth = stats.max_depth.max(depth);
+      match ast {
+        Ast::String(_) => stats.strings += 1,
+        Ast::Number(_) => stats.numbers += 1,
+        Ast::Boolean(_) => stats.booleans += 1,
+        Ast::Null(_) => stats.nulls += 1,
+        Ast::Object(ast) => {
+          stats.objects += 1;
+          stack.extend(ast.value.iter().map(|p| (p.value.as_ref(), depth + 1)));
+        }
+        Ast::Array(ast) => {
+          stats.arrays += 1;
+          stack.extend(ast.value.iter().map(|item| (item.as_ref(), depth + 1)));
+        }
+        // property 本身不计数，只统计它的值；单独的 key 算作字符串
+        Ast::Property(ast) => {
+          stack.push((ast.value.as_ref(), depth));
+          continue;
+        }
+        Ast::Identifier(_) => stats.strings += 1,
+      }
+      stats.total_nodes += 1;
+    }
+
+    stats
+  }
+
+  // 值节点的总数（string、number、boolean、null、object、array），property 和 key 不单独计数
+  pub fn node_count(&self) -> usize {
+    self.stats().total_nodes
+  }
+
+  // 标量和空容器的深度为 1
+  pub fn depth(&self) -> usize {
+    self.stats().max_depth
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::json;
+
+  #[test]
+  fn test_node_count_and_depth() {
+    let json = json!({
+      "a": [1, 2, {"b": null}],
+      "c": {"d": {"e": []}},
+      "f": "g",
+    });
+
+    // 根对象、数组、1、2、{"b"}、null、{"d"}、{"e"}、[]、"g"
+    assert_eq!(json.node_count(), 10);
+    assert_eq!(json.depth(), 4);
+
+    assert_eq!(json!(1).node_count(), 1);
+    assert_eq!(json!(1).depth(), 1);
+    assert_eq!(json!([]).depth(), 1);
+    assert_eq!(json!([[]]).depth(), 2);
+  }
+
+  #[test]
+  fn test_stats() {
+    let json = json!({
+      "a": [1, 2.5, {"b": null}],
+      "c": {"d": {"e": [], "f": true}},
+      "g": "h",
+      "i": ["j", false, null],
+    });
+
+    assert_eq!(
+      json.stats(),
+      Stats {
+        strings: 2,
+        numbers: 2,
+        booleans: 2,
+        nulls: 2,
+        objects: 4,
+        arrays: 3,
+        total_nodes: 15,
+        max_depth: 4,
+      }
+    );
+    assert_eq!(json.stats().total_nodes, json.node_count());
+    assert_eq!(json.stats().max_depth, json.depth());
+    assert_eq!(
+      json!("x").stats(),
+      Stats {
+        strings: 1,
+        total_nodes: 1,
+        max_depth: 1,
+        ..Stats::default()
+      }
+    );
+  }
+}
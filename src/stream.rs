@@ -0,0 +1,223 @@
+use crate::{
+  error::ParseError,
+  parser::Parser,
+  span::{Loc, Span},
+  tokenizer::{Token, Tokenizer},
+  Json,
+};
+
+pub struct StreamParser {
+  // 尚未切分成 token 的剩余文本
+  pending: String,
+  // pending 起始处在整个文档中的位置
+  origin: Loc,
+  tokens: Vec<Token>,
+  // pending 开头是未闭合的字符串时，记录已扫描的部分是否停在反斜杠之后
+  open_string: Option<bool>,
+}
+
+impl StreamParser {
+  pub fn new() -> Self {
+    Self {
+      pending: String::new(),
+      origin: Loc {
+        line: 1,
+        column: 1,
+        offset: 0,
+      },
+      tokens: vec![],
+      open_string: None,
+    }
+  }
+
+  pub fn feed(&mut self, chunk: &str) {
+    // 上次剩下未完成的 token 时，只有新内容可能让它结束才重新切分，
+    // 否则一个很长的字符串分成很多块传入时，每次都要从头扫描
+    let waiting = match self.open_string {
+      Some(escaped) => match scan_string(chunk, escaped) {
+        Ok(()) => false,
+        Err(escaped) => {
+          self.open_string = Some(escaped);
+          true
+        }
+      },
+      // 数字和关键字需要后面跟着分隔符才算结束
+      None => !self.pending.trim_start().is_empty() && !chunk.contains(is_delimiter),
+    };
+    self.pending.push_str(chunk);
+
+    if waiting {
+      return;
+    }
+
+    let mut tokenizer = tokenizer(&self.pending, &self.origin);
+    let mut consumed = tokenizer.loc();
+
+    // 只保留完整的 token：数字和关键字后面必须跟着分隔符才算结束（如 `tr`、`1.`），
+    // 出错也可能只是因为输入还没到齐，这两种情况都留到下次再切分
+    while let Ok(Some(mut token)) = tokenizer.next_token() {
+      let complete = match token {
+        Token::Number(_) | Token::Boolean(_) | Token::Null(_) | Token::Identifier(_) => {
+          tokenizer.peek().map_or(false, is_delimiter)
+        }
+        _ => true,
+      };
+
+      if !complete {
+        break;
+      }
+
+      shift_span(token.get_span_mut(), &self.origin);
+      self.tokens.push(token);
+      consumed = tokenizer.loc();
+    }
+
+    self.pending = self.pending.chars().skip(consumed.offset).collect();
+    self.origin = shift_loc(&consumed, &self.origin);
+    self.open_string = self
+      .pending
+      .trim_start()
+      .strip_prefix('"')
+      .and_then(|rest| scan_string(rest, false).err());
+  }
+
+  pub fn finish(mut self) -> Result<Json, ParseError> {
+    let mut tokenizer = tokenizer(&self.pending, &self.origin);
+
+    while let Some(mut token) = tokenizer.next_token().map_err(|mut e| {
+      if let Some(span) = e.span.as_mut() {
+        shift_span(span, &self.origin);
+      }
+      e
+    })? {
+      shift_span(token.get_span_mut(), &self.origin);
+      self.tokens.push(token);
+    }
+
+    Parser::new(&self.tokens).parse()
+  }
+}
+
+// 只有文档开头才需要跳过 BOM
+fn tokenizer<'a>(pending: &'a str, origin: &Loc) -> Tokenizer<'a> {
+  if origin.offset == 0 {
+    Tokenizer::new(pending)
+  } else {
+    Tokenizer::resume(pending)
+  }
+}
+
+fn is_delimiter(c: char) -> bool {
+  c.is_whitespace() || ",:[]{}\"".contains(c)
+}
+
+// 查找字符串中未转义的引号，找不到时返回末尾是否停在反斜杠之后
+fn scan_string(text: &str, mut escaped: bool) -> Result<(), bool> {
+  for c in text.chars() {
+    match c {
+      _ if escaped => escaped = false,
+      '\\' => escaped = true,
+      '"' => return Ok(()),
+      _ => {}
+    }
+  }
+
+  Err(escaped)
+}
+
+impl Default for StreamParser {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+// 把相对 pending 的位置换算成相对整个文档的位置
+fn shift_loc(loc: &Loc, origin: &Loc) -> Loc {
+  Loc {
+    line: origin.line + loc.line - 1,
+    column: if loc.line == 1 {
+      origin.column + loc.column - 1
+    } else {
+      loc.column
+    },
+    offset: origin.offset + loc.offset,
+  }
+}
+
+fn shift_span(span: &mut Span, origin: &Loc) {
+  span.start = shift_loc(&span.start, origin);
+  span.end = shift_loc(&span.end, origin);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const INPUT: &str =
+    "{\r\n  \"hello\": [\"wörld\", -1.5e3, true,\n null, {\"a\\u0041\": []}],\n  \"f\": false\n}";
+
+  #[test]
+  fn test_split_at_every_index() {
+    let expected = Json::parse(INPUT).unwrap();
+    let chars = INPUT.chars().collect::<Vec<char>>();
+
+    for i in 0..=chars.len() {
+      let mut parser = StreamParser::new();
+      parser.feed(&chars[..i].iter().collect::<String>());
+      parser.feed(&chars[i..].iter().collect::<String>());
+
+      assert_eq!(parser.finish().unwrap(), expected, "split at {}", i);
+    }
+  }
+
+  #[test]
+  fn test_feed_char_by_char() {
+    let mut parser = StreamParser::new();
+
+    for c in INPUT.chars() {
+      parser.feed(&c.to_string());
+    }
+
+    assert_eq!(parser.finish().unwrap(), Json::parse(INPUT).unwrap());
+  }
+
+  #[test]
+  fn test_error_location() {
+    let mut parser = StreamParser::new();
+    parser.feed("[1,\n  2,");
+    parser.feed(" x]");
+
+    let span = parser.finish().unwrap_err().span.unwrap();
+    assert_eq!((span.start.line, span.start.column), (2, 6));
+  }
+
+  #[test]
+  fn test_large_string_in_small_chunks() {
+    // 块的边界会落在转义序列中间
+    let value = "ab, [c]\\\"d\\\\".repeat(40_000);
+    let input = format!("[\"{}\", 1]", value);
+
+    let mut parser = StreamParser::new();
+    let chars = input.chars().collect::<Vec<char>>();
+    for chunk in chars.chunks(255) {
+      parser.feed(&chunk.iter().collect::<String>());
+    }
+
+    let json = parser.finish().unwrap();
+    assert_eq!(json, Json::parse(&input).unwrap());
+    assert_eq!(json.pointer("/0").unwrap().as_str().unwrap().len(), 400_000);
+  }
+
+  #[test]
+  fn test_bom() {
+    let mut parser = StreamParser::new();
+    parser.feed("\u{FEFF}");
+    parser.feed("[1]");
+    assert!(parser.finish().is_ok());
+
+    let mut parser = StreamParser::new();
+    parser.feed("[1,");
+    parser.feed("\u{FEFF}2]");
+    assert!(parser.finish().is_err());
+  }
+}
@@ -1,4 +1,10 @@
-use crate::span::{Loc, Span};
+use std::fmt;
+
+use crate::{
+  error::ParseError,
+  options::ParseOptions,
+  span::{Loc, Span},
+};
 
 #[derive(Debug, PartialEq)]
 pub enum Token {
@@ -12,9 +18,36 @@ pub enum Token {
   Number(NumberToken),
   Boolean(BoolToken),
   Null(NullToken),
+  Identifier(IdentifierToken),
+  // trivia，只有 Tokenizer::with_trivia 才会产生
+  Whitespace(WhitespaceToken),
+  Comment(CommentToken),
 }
 
 impl Token {
+  // 用于错误信息的名称
+  pub fn name(&self) -> &'static str {
+    match self {
+      Token::LeftBrace(_) => "'{'",
+      Token::RightBrace(_) => "'}'",
+      Token::LeftBracket(_) => "'['",
+      Token::RightBracket(_) => "']'",
+      Token::Colon(_) => "':'",
+      Token::Comma(_) => "','",
+      Token::String(_) => "string",
+      Token::Number(_) => "number",
+      Token::Boolean(_) => "boolean",
+      Token::Null(_) => "null",
+      Token::Identifier(_) => "identifier",
+      Token::Whitespace(_) => "whitespace",
+      Token::Comment(_) => "comment",
+    }
+  }
+
+  pub fn is_trivia(&self) -> bool {
+    matches!(self, Token::Whitespace(_) | Token::Comment(_))
+  }
+
   pub fn get_span(&self) -> &Span {
     match self {
       Token::LeftBrace(token) => &token.span,
@@ -27,6 +60,86 @@ impl Token {
       Token::Number(token) => &token.span,
       Token::Boolean(token) => &token.span,
       Token::Null(token) => &token.span,
+      Token::Identifier(token) => &token.span,
+      Token::Whitespace(token) => &token.span,
+      Token::Comment(token) => &token.span,
+    }
+  }
+
+  pub fn get_span_mut(&mut self) -> &mut Span {
+    match self {
+      Token::LeftBrace(token) => &mut token.span,
+      Token::RightBrace(token) => &mut token.span,
+      Token::LeftBracket(token) => &mut token.span,
+      Token::RightBracket(token) => &mut token.span,
+      Token::Colon(token) => &mut token.span,
+      Token::Comma(token) => &mut token.span,
+      Token::String(token) => &mut token.span,
+      Token::Number(token) => &mut token.span,
+      Token::Boolean(token) => &mut token.span,
+      Token::Null(token) => &mut token.span,
+      Token::Identifier(token) => &mut token.span,
+      Token::Whitespace(token) => &mut token.span,
+      Token::Comment(token) => &mut token.span,
+    }
+  }
+}
+
+// 语法高亮用的分类，字符串按所在位置分成 key 和值
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TokenKind {
+  Punctuation,
+  Key,
+  String,
+  Number,
+  Boolean,
+  Null,
+  Comment,
+}
+
+// 每个 token 的 span 和分类，后面紧跟 : 的字符串、标识符是 key，空白不输出
+pub fn classify_tokens(tokens: &[Token]) -> Vec<(Span, TokenKind)> {
+  let mut table = Vec::with_capacity(tokens.len());
+
+  for (index, token) in tokens.iter().enumerate() {
+    let kind = match token {
+      Token::String(_) | Token::Identifier(_) => {
+        let next = tokens[index + 1..].iter().find(|token| !token.is_trivia());
+        match next {
+          Some(Token::Colon(_)) => TokenKind::Key,
+          _ => TokenKind::String,
+        }
+      }
+      Token::Number(_) => TokenKind::Number,
+      Token::Boolean(_) => TokenKind::Boolean,
+      Token::Null(_) => TokenKind::Null,
+      Token::Comment(_) => TokenKind::Comment,
+      Token::Whitespace(_) => continue,
+      _ => TokenKind::Punctuation,
+    };
+    table.push((token.get_span().clone(), kind));
+  }
+
+  table
+}
+
+// 符号直接显示本身，其他 token 显示类型和原文，如 `string "foo"`、`number 3.14`
+impl fmt::Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Token::LeftBrace(_) => write!(f, "{{"),
+      Token::RightBrace(_) => write!(f, "}}"),
+      Token::LeftBracket(_) => write!(f, "["),
+      Token::RightBracket(_) => write!(f, "]"),
+      Token::Colon(_) => write!(f, ":"),
+      Token::Comma(_) => write!(f, ","),
+      Token::String(token) => write!(f, "string {}", token.value),
+      Token::Number(token) => write!(f, "number {}", token.raw),
+      Token::Boolean(token) => write!(f, "boolean {}", token.value),
+      Token::Null(_) => write!(f, "null"),
+      Token::Identifier(token) => write!(f, "identifier {}", token.value),
+      Token::Whitespace(_) => write!(f, "whitespace"),
+      Token::Comment(token) => write!(f, "comment {}", token.value),
     }
   }
 }
@@ -75,6 +188,8 @@ pub struct StringToken {
 #[derive(Debug, PartialEq)]
 pub struct NumberToken {
   pub value: f64,
+  // 原始写法，如 `1.0`、`1e3`
+  pub raw: String,
   pub span: Span,
 }
 
@@ -89,6 +204,25 @@ pub struct NullToken {
   pub span: Span,
 }
 
+#[derive(Debug, PartialEq)]
+pub struct IdentifierToken {
+  pub value: String,
+  pub span: Span,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct WhitespaceToken {
+  pub value: String,
+  pub span: Span,
+}
+
+// 包含 `//`、`/* */` 本身
+#[derive(Debug, PartialEq)]
+pub struct CommentToken {
+  pub value: String,
+  pub span: Span,
+}
+
 enum StringState {
   Start,
   QuoteOrChar,
@@ -106,60 +240,223 @@ enum NumberState {
   ExpSignOrDigit,
 }
 
-const TRUE_LEN: usize = "true".len();
-const FALSE_LEN: usize = "false".len();
-const NULL_LEN: usize = "null".len();
-
-pub struct Tokenizer {
-  chars: Vec<char>,
-  len: usize,
+pub struct Tokenizer<'a> {
+  input: &'a str,
+  // 字节位置，用于切片
+  pos: usize,
+  // 字符位置，即 Loc.offset
   index: usize,
   line: usize,
   column: usize,
+  options: ParseOptions,
+  // 是否输出空白和注释
+  trivia: bool,
 }
 
-impl Tokenizer {
-  pub fn new(input: &str) -> Self {
-    let chars = input.chars().collect::<Vec<char>>();
-    let len = chars.len();
+impl<'a> Tokenizer<'a> {
+  pub fn new(input: &'a str) -> Self {
+    let mut tokenizer = Self::resume(input);
 
+    // 只跳过文档开头的 BOM，偏移仍按原文计算
+    if tokenizer.peek() == Some('\u{FEFF}') {
+      tokenizer.pos = '\u{FEFF}'.len_utf8();
+      tokenizer.index = 1;
+    }
+
+    tokenizer
+  }
+
+  pub fn with_options(input: &'a str, options: ParseOptions) -> Self {
     Self {
-      chars,
-      len,
+      options,
+      ..Self::new(input)
+    }
+  }
+
+  // 保留空白和注释，拼起来就是原文（BOM 除外）
+  pub fn with_trivia(input: &'a str, options: ParseOptions) -> Self {
+    Self {
+      trivia: true,
+      ..Self::with_options(input, options)
+    }
+  }
+
+  // 从文档中间继续切分，不处理 BOM
+  pub fn resume(input: &'a str) -> Self {
+    Self {
+      input,
+      pos: 0,
       index: 0,
       line: 1,
       column: 1,
+      options: ParseOptions::default(),
+      trivia: false,
+    }
+  }
+
+  // 从 loc 所在位置继续切分完整的 input，loc 一般来自之前某个 Tokenizer 的 loc()，
+  // 这样只需要重新切分修改过的部分，得到的 span 仍然相对整个文档
+  pub fn resume_at(input: &'a str, loc: Loc) -> Self {
+    let pos = input
+      .char_indices()
+      .nth(loc.offset)
+      .map_or(input.len(), |(pos, _)| pos);
+
+    Self {
+      pos,
+      index: loc.offset,
+      line: loc.line,
+      column: loc.column,
+      ..Self::resume(input)
     }
   }
 
-  pub fn tokenize(&mut self) -> Result<Vec<Token>, String> {
+  pub fn tokenize(&mut self) -> Result<Vec<Token>, ParseError> {
     let mut tokens = Vec::new();
+    self.tokenize_into(&mut tokens)?;
+
+    Ok(tokens)
+  }
+
+  // 追加到已有的 Vec 中，可以复用它的容量
+  pub fn tokenize_into(&mut self, tokens: &mut Vec<Token>) -> Result<(), ParseError> {
+    self.check_total_len()?;
+
+    while let Some(token) = self.next_token()? {
+      tokens.push(token);
+    }
+
+    Ok(())
+  }
+
+  // 直接调用 next_token 时需要自己先检查 max_total_len
+  pub(crate) fn check_total_len(&self) -> Result<(), ParseError> {
+    match self.options.max_total_len {
+      Some(max) if self.input.len() > max => Err(ParseError::limit_exceeded(
+        format!("Input exceeds the maximum length of {} bytes", max),
+        None,
+      )),
+      _ => Ok(()),
+    }
+  }
 
-    while self.index < self.len {
-      if let Some(_) = self.whitespace() {
+  // 跳过空白并读取下一个 token，到达末尾时返回 None
+  pub fn next_token(&mut self) -> Result<Option<Token>, ParseError> {
+    while let Some(c) = self.peek() {
+      let start_loc = self.loc();
+      let start_pos = self.pos;
+
+      if self.whitespace().is_some() {
+        // 连续的空白合并成一个 token
+        while self.whitespace().is_some() {}
+
+        if self.trivia {
+          return Ok(Some(Token::Whitespace(WhitespaceToken {
+            value: self.slice(start_pos).to_string(),
+            span: Span {
+              start: start_loc,
+              end: self.loc(),
+            },
+          })));
+        }
+        continue;
+      }
+
+      if let Some(token) = self.comment()? {
+        if self.trivia {
+          return Ok(Some(token));
+        }
         continue;
       }
 
+      if let Some(token) = self.string()? {
+        return Ok(Some(token));
+      }
+
+      if let Some(token) = self.number() {
+        self.check_number_range(&token)?;
+        return Ok(Some(token));
+      }
+
       let token = self
         .punctuation()
-        .or_else(|| self.string())
-        .or_else(|| self.number())
+        .or_else(|| self.non_finite())
         .or_else(|| self.boolean())
-        .or_else(|| self.null());
+        .or_else(|| self.null())
+        .or_else(|| self.identifier());
 
       if let Some(token) = token {
-        tokens.push(token);
+        return Ok(Some(token));
+      } else if is_identifier_start(c) {
+        // 整个单词一起报错，如 `nullable`
+        let len = self.word_len();
+        return Err(ParseError::new(
+          format!("Unexpected identifier {:?}", &self.rest()[..len]),
+          self.line_span(None, self.index + len),
+        ));
       } else {
-        return Err(format!(
-          "Unexpected char {:#?} at {:#?}:{:#?}",
-          self.chars.get(self.index).unwrap(),
-          self.line,
-          self.column
+        return Err(ParseError::new(
+          format!("Unexpected char {:?}", c),
+          self.line_span(None, self.index + 1),
         ));
       }
     }
 
-    Ok(tokens)
+    Ok(None)
+  }
+
+  pub fn loc(&self) -> Loc {
+    Loc {
+      line: self.line,
+      column: self.column,
+      offset: self.index,
+    }
+  }
+
+  // 当前的字节位置
+  pub fn byte_pos(&self) -> usize {
+    self.pos
+  }
+
+  // 还没有切分的部分
+  pub fn remaining(&self) -> &'a str {
+    self.rest()
+  }
+
+  pub fn peek(&self) -> Option<char> {
+    self.rest().chars().next()
+  }
+
+  fn peek_nth(&self, n: usize) -> Option<char> {
+    self.rest().chars().nth(n)
+  }
+
+  fn rest(&self) -> &'a str {
+    &self.input[self.pos..]
+  }
+
+  // 从 start（字节位置）到当前位置的原文
+  fn slice(&self, start: usize) -> &'a str {
+    &self.input[start..self.pos]
+  }
+
+  // 前进一个字符，不处理换行
+  fn bump(&mut self) {
+    if let Some(c) = self.peek() {
+      self.pos += c.len_utf8();
+      self.index += 1;
+      self.column += 1;
+    }
+  }
+
+  // 前进 len 个 ASCII 字符，返回经过的 span
+  fn advance(&mut self, len: usize) -> Span {
+    let span = self.line_span(None, self.index + len);
+    self.pos += len;
+    self.index += len;
+    self.column += len;
+
+    span
   }
 
   fn line_span(&self, start_loc: Option<Loc>, end_index: usize) -> Span {
@@ -181,146 +478,184 @@ impl Tokenizer {
     }
   }
 
-  fn substring(&self, start: usize, end: usize) -> String {
-    self.chars.iter().skip(start).take(end - start).collect()
-  }
-
   fn whitespace(&mut self) -> Option<()> {
-    let c = self.chars.get(self.index).unwrap();
-
-    match c {
-      ' ' | '\t' => {
-        self.index += 1;
-        self.column += 1;
+    match self.peek() {
+      Some('\t') if self.options.tab_width > 0 => {
+        // 跳到下一个制表位
+        let width = self.options.tab_width;
+        let column = (self.column - 1) / width * width + width + 1;
+        self.bump();
+        self.column = column;
         Some(())
       }
+      Some(' ' | '\t') => {
+        self.bump();
+        Some(())
+      }
+      _ => self.newline().then_some(()),
+    }
+  }
+
+  fn newline(&mut self) -> bool {
+    match self.peek() {
       // CR (Unix)
-      '\r' => {
+      Some('\r') => {
+        self.pos += 1;
         self.index += 1;
-        self.line += 1;
-        self.column = 1;
 
         // CRLF (Windows)
-        if *c == '\n' {
+        if let Some('\n') = self.peek() {
+          self.pos += 1;
           self.index += 1;
         }
-
-        Some(())
       }
       // LF (MacOS)
-      '\n' => {
+      Some('\n') => {
+        self.pos += 1;
         self.index += 1;
-        self.line += 1;
-        self.column = 1;
-        Some(())
       }
-      _ => None,
+      _ => return false,
     }
-  }
 
-  fn punctuation(&mut self) -> Option<Token> {
-    let c = self.chars.get(self.index).unwrap();
+    self.line += 1;
+    self.column = 1;
+    true
+  }
 
-    match c {
-      '{' => {
-        let token = Token::LeftBrace(LeftBraceToken {
-          span: self.line_span(None, self.index + 1),
-        });
-        self.index += 1;
-        self.column += 1;
+  // `// ...` 到行尾（不含换行），`/* ... */` 可以跨行
+  fn comment(&mut self) -> Result<Option<Token>, ParseError> {
+    if !self.options.allow_comments || self.peek() != Some('/') {
+      return Ok(None);
+    }
 
-        Some(token)
-      }
-      '}' => {
-        let token = Token::RightBrace(RightBraceToken {
-          span: self.line_span(None, self.index + 1),
-        });
-        self.index += 1;
-        self.column += 1;
+    let start_loc = self.loc();
+    let start_pos = self.pos;
 
-        Some(token)
+    match self.peek_nth(1) {
+      Some('/') => {
+        while let Some(c) = self.peek() {
+          if c == '\r' || c == '\n' {
+            break;
+          }
+          self.bump();
+        }
       }
-      '[' => {
-        let token = Token::LeftBracket(LeftBracketToken {
-          span: self.line_span(None, self.index + 1),
-        });
-        self.index += 1;
-        self.column += 1;
-
-        Some(token)
+      Some('*') => {
+        self.advance(2);
+
+        loop {
+          match self.peek() {
+            Some('*') if self.peek_nth(1) == Some('/') => {
+              self.advance(2);
+              break;
+            }
+            Some(_) => {
+              if !self.newline() {
+                self.bump();
+              }
+            }
+            None => {
+              return Err(ParseError::new(
+                "Unterminated comment",
+                Span {
+                  start: start_loc,
+                  end: self.loc(),
+                },
+              ))
+            }
+          }
+        }
       }
-      ']' => {
-        let token = Token::RightBracket(RightBracketToken {
-          span: self.line_span(None, self.index + 1),
-        });
-        self.index += 1;
-        self.column += 1;
+      _ => return Ok(None),
+    }
 
-        Some(token)
-      }
-      ':' => {
-        let token = Token::Colon(ColonToken {
-          span: self.line_span(None, self.index + 1),
-        });
-        self.index += 1;
-        self.column += 1;
+    Ok(Some(Token::Comment(CommentToken {
+      value: self.slice(start_pos).to_string(),
+      span: Span {
+        start: start_loc,
+        end: self.loc(),
+      },
+    })))
+  }
 
-        Some(token)
-      }
-      ',' => {
-        let token = Token::Comma(CommaToken {
-          span: self.line_span(None, self.index + 1),
-        });
-        self.index += 1;
-        self.column += 1;
+  fn punctuation(&mut self) -> Option<Token> {
+    let span = self.line_span(None, self.index + 1);
+
+    let token = match self.peek()? {
+      '{' => Token::LeftBrace(LeftBraceToken { span }),
+      '}' => Token::RightBrace(RightBraceToken { span }),
+      '[' => Token::LeftBracket(LeftBracketToken { span }),
+      ']' => Token::RightBracket(RightBracketToken { span }),
+      ':' => Token::Colon(ColonToken { span }),
+      ',' => Token::Comma(CommaToken { span }),
+      _ => return None,
+    };
+    self.bump();
 
-        Some(token)
-      }
-      _ => None,
-    }
+    Some(token)
   }
 
-  fn string(&mut self) -> Option<Token> {
+  fn string(&mut self) -> Result<Option<Token>, ParseError> {
     let mut state = StringState::Start;
-    let start_loc = Loc {
-      line: self.line,
-      column: self.column,
-      offset: self.index,
-    };
+    let mut quote = '"';
+    let start_loc = self.loc();
+    let start_pos = self.pos;
+
+    while let Some(c) = self.peek() {
+      // 已读取的内容（不含开头引号）超出限制时立即停止
+      if let Some(max) = self.options.max_string_len {
+        if self.pos - start_pos > max + 1 {
+          return Err(ParseError::limit_exceeded(
+            format!("String exceeds the maximum length of {} bytes", max),
+            Some(Span {
+              start: start_loc,
+              end: self.loc(),
+            }),
+          ));
+        }
+      }
 
-    while let Some(c) = self.chars.get(self.index) {
       match state {
         StringState::Start => match c {
           // 开始引号
-          '"' => {
+          '"' | '\'' if c == '"' || self.options.allow_single_quotes => {
+            quote = c;
             state = StringState::QuoteOrChar;
-            self.index += 1;
-            self.column += 1;
+            self.bump();
           }
-          _ => return None,
+          _ => return Ok(None),
         },
         StringState::QuoteOrChar => match c {
           // 结束引号
-          '"' => {
-            let token = Token::String(StringToken {
-              value: self.substring(start_loc.offset, self.index + 1),
-              span: self.line_span(Some(start_loc), self.index + 1),
-            });
-            self.index += 1;
-            self.column += 1;
-
-            return Some(token);
+          c if c == quote => {
+            self.bump();
+
+            // 字符串内可能有换行，结束位置取当前行列
+            return Ok(Some(Token::String(StringToken {
+              value: self.slice(start_pos).to_string(),
+              span: Span {
+                start: start_loc,
+                end: self.loc(),
+              },
+            })));
           }
           // 转义字符
           '\\' => {
             state = StringState::Escape;
-            self.index += 1;
-            self.column += 1;
+            self.bump();
+          }
+          // 未转义的控制字符，JSON 中必须写成 \n、\u0001 这样的转义
+          c if c < '\u{20}' && !self.options.allow_control_chars => {
+            return Err(ParseError::new(
+              format!("Unescaped control character U+{:04X} in string", c as u32),
+              self.line_span(None, self.index + 1),
+            ));
           }
           // 其他字符
           _ => {
-            self.index += 1;
-            self.column += 1;
+            if !self.newline() {
+              self.bump();
+            }
           }
         },
         // 转义字符
@@ -329,45 +664,59 @@ impl Tokenizer {
             // Unicode 字符
             'u' => {
               // 后面跟 4 位十六进制数字
-              for i in 0..4 {
-                if let Some(hex_c) = self.chars.get(self.index + i + 1) {
-                  if is_hex(hex_c) {
-                    self.index += 1;
-                    self.column += 1;
-                    continue;
+              for _ in 0..4 {
+                self.bump();
+
+                match self.peek() {
+                  Some(hex_c) if is_hex(&hex_c) => {}
+                  _ => {
+                    return Err(ParseError::new(
+                      "Invalid unicode escape",
+                      self.line_span(None, self.index + 1),
+                    ))
                   }
                 }
-
-                return None;
               }
 
+              self.bump();
               state = StringState::QuoteOrChar;
             }
             // 其他转义字符
             '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' => {
-              self.index += 1;
-              self.column += 1;
+              self.bump();
               state = StringState::QuoteOrChar;
             }
-            _ => return None,
+            '\'' if self.options.allow_single_quotes => {
+              self.bump();
+              state = StringState::QuoteOrChar;
+            }
+            _ => {
+              return Err(ParseError::new(
+                format!("Invalid escape character {:?}", c),
+                self.line_span(None, self.index + 1),
+              ))
+            }
           }
         }
       }
     }
 
-    None
+    Err(ParseError::new(
+      "Unterminated string",
+      Span {
+        start: start_loc,
+        end: self.loc(),
+      },
+    ))
   }
 
   fn number(&mut self) -> Option<Token> {
     let mut state = NumberState::Start;
     let mut parsed_index: usize = 0;
-    let start_loc = Loc {
-      line: self.line,
-      column: self.column,
-      offset: self.index,
-    };
+    let start_loc = self.loc();
+    let start_pos = self.pos;
 
-    while let Some(c) = self.chars.get(self.index) {
+    while let Some(c) = self.peek() {
       match state {
         NumberState::Start => match c {
           '-' => {
@@ -433,7 +782,7 @@ impl Tokenizer {
           _ => break,
         },
         NumberState::Exp => match c {
-          '-' => {
+          '-' | '+' => {
             state = NumberState::ExpSignOrDigit;
           }
           '0'..='9' => {
@@ -442,76 +791,142 @@ impl Tokenizer {
           }
           _ => break,
         },
+        // 指数部分只能继续跟数字，不能再出现小数点或第二个 e
         NumberState::ExpSignOrDigit => match c {
           '0'..='9' => {
-            state = NumberState::Fraction;
             parsed_index = self.index + 1;
           }
           _ => break,
         },
       };
 
-      self.index += 1;
-      self.column += 1;
+      self.bump();
     }
 
+    // 回退到起点，再只前进合法的部分，未完成的小数点、指数不应被吞掉
+    self.pos = start_pos;
+    self.index = start_loc.offset;
+    self.column = start_loc.column;
+
     if parsed_index > 0 {
-      let value = self
-        .substring(start_loc.offset, parsed_index)
-        .parse::<f64>()
-        .unwrap();
-      let token = Token::Number(NumberToken {
+      let len = parsed_index - start_loc.offset;
+      // 数字只含 ASCII，字符数等于字节数
+      let raw = self.rest()[..len].to_string();
+      let value = raw.parse::<f64>().unwrap();
+
+      return Some(Token::Number(NumberToken {
         value,
-        span: self.line_span(Some(start_loc), parsed_index),
-      });
+        raw,
+        span: self.advance(len),
+      }));
+    }
+
+    None
+  }
 
-      return Some(token);
+  // 形如有限数的字面量解析成了 inf，如 1e400
+  fn check_number_range(&self, token: &Token) -> Result<(), ParseError> {
+    match token {
+      Token::Number(number) if self.options.reject_number_overflow && !number.value.is_finite() => {
+        Err(ParseError::new(
+          format!("Number {} is out of range", number.raw),
+          number.span.clone(),
+        ))
+      }
+      _ => Ok(()),
+    }
+  }
+
+  fn non_finite(&mut self) -> Option<Token> {
+    if !self.options.allow_non_finite {
+      return None;
+    }
+
+    for (literal, value) in [
+      ("NaN", f64::NAN),
+      ("Infinity", f64::INFINITY),
+      ("-Infinity", f64::NEG_INFINITY),
+    ] {
+      if let Some(span) = self.keyword(literal) {
+        return Some(Token::Number(NumberToken {
+          value,
+          raw: literal.to_string(),
+          span,
+        }));
+      }
     }
 
     None
   }
 
-  fn boolean(&mut self) -> Option<Token> {
-    if self.substring(self.index, self.index + TRUE_LEN) == "true" {
-      let token = Token::Boolean(BoolToken {
-        value: true,
-        span: self.line_span(None, self.index + TRUE_LEN),
-      });
-      self.index += TRUE_LEN;
-      self.column += TRUE_LEN;
+  // 关键字后面不能紧跟标识符字符，`nullable`、`truex` 不是关键字
+  fn keyword(&mut self, literal: &str) -> Option<Span> {
+    let rest = self.rest();
 
-      return Some(token);
+    // starts_with 会先比较剩余长度，末尾不足时直接返回 false
+    if !rest.starts_with(literal) {
+      return None;
     }
 
-    if self.substring(self.index, self.index + FALSE_LEN) == "false" {
-      let token = Token::Boolean(BoolToken {
-        value: false,
-        span: self.line_span(None, self.index + FALSE_LEN),
-      });
-      self.index += FALSE_LEN;
-      self.column += FALSE_LEN;
+    match rest[literal.len()..].chars().next() {
+      Some(c) if is_identifier_char(c) => None,
+      _ => Some(self.advance(literal.len())),
+    }
+  }
 
-      return Some(token);
+  fn boolean(&mut self) -> Option<Token> {
+    for (literal, value) in [("true", true), ("false", false)] {
+      if let Some(span) = self.keyword(literal) {
+        return Some(Token::Boolean(BoolToken { value, span }));
+      }
     }
 
     None
   }
 
   fn null(&mut self) -> Option<Token> {
-    if self.substring(self.index, self.index + NULL_LEN) == "null" {
-      let token = Token::Null(NullToken {
-        span: self.line_span(None, self.index + NULL_LEN),
-      });
-      self.index += NULL_LEN;
-      self.column += NULL_LEN;
+    self
+      .keyword("null")
+      .map(|span| Token::Null(NullToken { span }))
+  }
+
+  // 从当前位置开始的标识符长度
+  fn word_len(&self) -> usize {
+    let rest = self.rest();
 
-      return Some(token);
+    rest
+      .find(|c: char| !is_identifier_char(c))
+      .unwrap_or(rest.len())
+  }
+
+  // 未加引号的 key：[A-Za-z_$][A-Za-z0-9_$]*
+  fn identifier(&mut self) -> Option<Token> {
+    if !self.options.allow_unquoted_keys && !self.options.allow_bare_strings {
+      return None;
     }
 
-    None
+    match self.peek() {
+      Some(c) if is_identifier_start(c) => {}
+      _ => return None,
+    }
+
+    let len = self.word_len();
+
+    Some(Token::Identifier(IdentifierToken {
+      value: self.rest()[..len].to_string(),
+      span: self.advance(len),
+    }))
   }
 }
 
+fn is_identifier_start(c: char) -> bool {
+  c.is_ascii_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_identifier_char(c: char) -> bool {
+  c.is_ascii_alphanumeric() || c == '_' || c == '$'
+}
+
 fn is_hex(c: &char) -> bool {
   *c >= '0' && *c <= '9' || *c >= 'a' && *c <= 'f' || *c >= 'A' && *c <= 'F'
 }
@@ -519,6 +934,7 @@ fn is_hex(c: &char) -> bool {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::error::ParseErrorKind;
 
   #[test]
   fn test_tokenizer() {
@@ -605,4 +1021,583 @@ mod tests {
       ]
     );
   }
+
+  #[test]
+  fn test_multiline_string_span() {
+    let options = ParseOptions {
+      allow_control_chars: true,
+      ..ParseOptions::default()
+    };
+    let mut tokenizer = Tokenizer::with_options("[\"a\nbc\", 1]", options);
+    let tokens = tokenizer.tokenize().unwrap();
+
+    assert_eq!(
+      tokens[1].get_span(),
+      &Span {
+        start: Loc {
+          line: 1,
+          column: 2,
+          offset: 1
+        },
+        end: Loc {
+          line: 2,
+          column: 4,
+          offset: 7
+        }
+      }
+    );
+    assert_eq!(
+      tokens[3].get_span().start,
+      Loc {
+        line: 2,
+        column: 6,
+        offset: 9
+      }
+    );
+  }
+
+  #[test]
+  fn test_control_chars_in_string() {
+    let err = Tokenizer::new("[\"a\tb\"]").tokenize().unwrap_err();
+    assert_eq!(err.message, "Unescaped control character U+0009 in string");
+    assert_eq!(err.span.unwrap().start.offset, 3);
+
+    let err = Tokenizer::new("{\"k\": \"x\ny\"}").tokenize().unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "Unescaped control character U+000A in string at line 1, column 9"
+    );
+
+    // 转义后的写法以及字符串外的空白不受影响
+    assert!(Tokenizer::new("[\"a\\tb\\n\",\t\n1]").tokenize().is_ok());
+
+    let options = ParseOptions {
+      allow_control_chars: true,
+      ..ParseOptions::default()
+    };
+    let tokens = Tokenizer::with_options("[\"a\tb\", \"x\ny\u{1}\"]", options)
+      .tokenize()
+      .unwrap();
+    assert!(matches!(&tokens[3], Token::String(t) if t.value == "\"x\ny\u{1}\""));
+  }
+
+  #[test]
+  fn test_multiline_string_crlf() {
+    let options = ParseOptions {
+      allow_control_chars: true,
+      ..ParseOptions::default()
+    };
+    // CRLF 算一个换行，单独的 CR 也算换行
+    let input = "{\"k\": \"a\r\nb\rcd\", \"n\": 1}";
+    let json = crate::Json::parse_with_options(input, &options).unwrap();
+
+    let span = json.pointer("/k").unwrap().get_span();
+    assert_eq!(
+      span.end,
+      Loc {
+        line: 3,
+        column: 4,
+        offset: 15
+      }
+    );
+    assert_eq!(span.slice(input), "\"a\r\nb\rcd\"");
+    assert_eq!(
+      json.pointer("/n").unwrap().get_span().start,
+      Loc {
+        line: 3,
+        column: 11,
+        offset: 22
+      }
+    );
+  }
+
+  #[test]
+  fn test_crlf_counts_one_line() {
+    let mut tokenizer = Tokenizer::new("[\r\n1]");
+    let tokens = tokenizer.tokenize().unwrap();
+
+    assert_eq!(
+      tokens[1].get_span().start,
+      Loc {
+        line: 2,
+        column: 1,
+        offset: 3
+      }
+    );
+  }
+
+  #[test]
+  fn test_string_errors() {
+    let tokens = Tokenizer::new("\"\\u0041bc\"").tokenize().unwrap();
+    assert_eq!(tokens[0].get_span().end.offset, 10);
+
+    let err = Tokenizer::new("[\"abc").tokenize().unwrap_err();
+    assert_eq!(err.message, "Unterminated string");
+
+    let err = Tokenizer::new("\"\\x\"").tokenize().unwrap_err();
+    assert_eq!(err.message, "Invalid escape character 'x'");
+  }
+
+  #[test]
+  fn test_number_keeps_trailing_point() {
+    let err = Tokenizer::new("[1.]").tokenize().unwrap_err();
+    assert_eq!(err.message, "Unexpected char '.'");
+    assert_eq!(err.span.unwrap().start.offset, 2);
+  }
+
+  #[test]
+  fn test_number_exponent() {
+    for (input, value) in [
+      ("1e+5", 1e5),
+      ("1E+10", 1e10),
+      ("1e-5", 1e-5),
+      ("-2.5E+0", -2.5),
+    ] {
+      let tokens = Tokenizer::new(input).tokenize().unwrap();
+      assert_eq!(tokens.len(), 1, "{}", input);
+      assert!(matches!(&tokens[0], Token::Number(t) if t.value == value && t.raw == input));
+    }
+
+    // 没有数字的指数不属于数字，留下的 e 报错
+    for input in ["1e+", "1e", "1E-", "1e+-5"] {
+      let err = Tokenizer::new(input).tokenize().unwrap_err();
+      assert_eq!(err.span.unwrap().start.offset, 1, "{}", input);
+    }
+    assert!(crate::Json::parse("[1e+]").is_err());
+
+    // 指数之后不能再有指数或小数部分
+    let err = Tokenizer::new("1e5e3").tokenize().unwrap_err();
+    assert_eq!(err.span.unwrap().start.offset, 3);
+    let err = Tokenizer::new("1e5.5").tokenize().unwrap_err();
+    assert_eq!(err.message, "Unexpected char '.'");
+  }
+
+  #[test]
+  fn test_bom() {
+    let tokens = Tokenizer::new("\u{FEFF}{}").tokenize().unwrap();
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(
+      tokens[0].get_span().start,
+      Loc {
+        line: 1,
+        column: 1,
+        offset: 1
+      }
+    );
+
+    let err = Tokenizer::new("{\u{FEFF}}").tokenize().unwrap_err();
+    assert_eq!(err.message, "Unexpected char '\\u{feff}'");
+    assert!(Tokenizer::new("\u{FEFF}\u{FEFF}{}").tokenize().is_err());
+  }
+
+  #[test]
+  fn test_single_quotes() {
+    let options = ParseOptions {
+      allow_single_quotes: true,
+      ..ParseOptions::default()
+    };
+
+    let tokens = Tokenizer::with_options("['hi', 'say \"x\" \\'y\\'', \"it's\"]", options.clone())
+      .tokenize()
+      .unwrap();
+    assert!(matches!(&tokens[1], Token::String(t) if t.value == "'hi'"));
+    assert!(matches!(&tokens[3], Token::String(t) if t.value == "'say \"x\" \\'y\\''"));
+    assert!(matches!(&tokens[5], Token::String(t) if t.value == "\"it's\""));
+
+    let err = Tokenizer::with_options("['abc]", options)
+      .tokenize()
+      .unwrap_err();
+    assert_eq!(err.message, "Unterminated string");
+
+    assert!(Tokenizer::new("['hi']").tokenize().is_err());
+  }
+
+  #[test]
+  fn test_identifier() {
+    let options = ParseOptions {
+      allow_unquoted_keys: true,
+      ..ParseOptions::default()
+    };
+
+    let tokens = Tokenizer::with_options("{$a_1: true}", options.clone())
+      .tokenize()
+      .unwrap();
+    assert!(matches!(&tokens[1], Token::Identifier(t) if t.value == "$a_1"));
+    assert_eq!(tokens[1].get_span().end.offset, 5);
+    assert!(matches!(&tokens[3], Token::Boolean(_)));
+
+    assert!(Tokenizer::with_options("{a-b: 1}", options)
+      .tokenize()
+      .is_err());
+    assert!(Tokenizer::new("{a: 1}").tokenize().is_err());
+  }
+
+  #[test]
+  fn test_trivia_round_trip() {
+    let options = ParseOptions {
+      allow_comments: true,
+      ..ParseOptions::default()
+    };
+
+    for input in [
+      "{\"a\": 1}",
+      "  [1,\t2 ,\r\n  3]\n",
+      "// head\n{\n  /* a\n  b */ \"a\": [true, null] // tail\n}\n",
+      "/**/1//",
+    ] {
+      let tokens = Tokenizer::with_trivia(input, options.clone())
+        .tokenize()
+        .unwrap();
+      let text = tokens
+        .iter()
+        .map(|token| match token {
+          Token::Whitespace(t) => t.value.clone(),
+          Token::Comment(t) => t.value.clone(),
+          Token::String(t) => t.value.clone(),
+          Token::Number(t) => t.raw.clone(),
+          Token::Identifier(t) => t.value.clone(),
+          Token::Boolean(t) => t.value.to_string(),
+          Token::Null(_) => "null".to_string(),
+          _ => token.name().trim_matches('\'').to_string(),
+        })
+        .collect::<String>();
+
+      assert_eq!(text, input);
+
+      // span 首尾相接
+      for pair in tokens.windows(2) {
+        assert_eq!(pair[0].get_span().end, pair[1].get_span().start);
+      }
+    }
+  }
+
+  #[test]
+  fn test_trivia_tokens() {
+    let options = ParseOptions {
+      allow_comments: true,
+      ..ParseOptions::default()
+    };
+    let tokens = Tokenizer::with_trivia(" \n /* x\n */[]", options.clone())
+      .tokenize()
+      .unwrap();
+
+    assert!(matches!(&tokens[0], Token::Whitespace(t) if t.value == " \n "));
+    assert!(matches!(&tokens[1], Token::Comment(t) if t.value == "/* x\n */"));
+    assert_eq!(
+      tokens[1].get_span().end,
+      Loc {
+        line: 3,
+        column: 4,
+        offset: 11
+      }
+    );
+    assert_eq!(tokens.iter().filter(|t| t.is_trivia()).count(), 2);
+
+    // 不保留 trivia 时注释直接跳过
+    let tokens = Tokenizer::with_options("[1, // x\n 2]", options.clone())
+      .tokenize()
+      .unwrap();
+    assert_eq!(tokens.len(), 5);
+
+    let err = Tokenizer::with_options("[1 /* x", options)
+      .tokenize()
+      .unwrap_err();
+    assert_eq!(err.message, "Unterminated comment");
+    assert!(Tokenizer::new("[1 // x\n]").tokenize().is_err());
+  }
+
+  #[test]
+  fn test_unicode_offsets() {
+    let options = ParseOptions {
+      allow_comments: true,
+      allow_unquoted_keys: true,
+      allow_control_chars: true,
+      ..ParseOptions::default()
+    };
+
+    for input in [
+      "[\"héllo\", \"日本語\", 1]",
+      "{\"😀\": [\"a\\u00e9\", -1.5e3], \"ö\":\n\"x\ny\"}",
+      "\u{FEFF}{\"é\" : true, k_1: null} /* ü */",
+    ] {
+      let chars = input.chars().collect::<Vec<char>>();
+      let tokens = Tokenizer::with_options(input, options.clone())
+        .tokenize()
+        .unwrap();
+
+      // offset 按字符计算，span 对应的原文就是 token 的内容
+      for token in &tokens {
+        let span = token.get_span();
+        let text = chars[span.start.offset..span.end.offset]
+          .iter()
+          .collect::<String>();
+
+        match token {
+          Token::String(t) => assert_eq!(t.value, text),
+          Token::Number(t) => assert_eq!(t.raw, text),
+          Token::Identifier(t) => assert_eq!(t.value, text),
+          Token::Boolean(t) => assert_eq!(t.value.to_string(), text),
+          Token::Null(_) => assert_eq!("null", text),
+          _ => assert_eq!(token.name().trim_matches('\''), text),
+        }
+      }
+    }
+
+    let tokens = Tokenizer::new("[\"日本\", 1]").tokenize().unwrap();
+    assert_eq!(
+      tokens[3].get_span().start,
+      Loc {
+        line: 1,
+        column: 8,
+        offset: 7
+      }
+    );
+  }
+
+  #[test]
+  fn test_flat_literals() {
+    let input = format!(
+      "[{}]",
+      ["true", "false", "null", "-0"].repeat(1000).join(",")
+    );
+    let tokens = Tokenizer::new(&input).tokenize().unwrap();
+
+    assert_eq!(tokens.len(), 2 + 4000 * 2 - 1);
+    assert!(matches!(&tokens[7], Token::Number(t) if t.raw == "-0"));
+    assert!(matches!(&tokens[7999], Token::Number(t) if t.raw == "-0"));
+    assert_eq!(
+      tokens.last().unwrap().get_span().end.offset,
+      input.chars().count()
+    );
+  }
+
+  #[test]
+  fn test_keyword_boundary() {
+    let tokens = Tokenizer::new("[true,false,null]").tokenize().unwrap();
+    assert_eq!(tokens.len(), 7);
+    assert!(Tokenizer::new("null").tokenize().is_ok());
+
+    let err = Tokenizer::new("[nullable]").tokenize().unwrap_err();
+    assert_eq!(err.message, "Unexpected identifier \"nullable\"");
+    let span = err.span.unwrap();
+    assert_eq!((span.start.offset, span.end.offset), (1, 9));
+
+    let err = Tokenizer::new("truex").tokenize().unwrap_err();
+    assert_eq!(err.message, "Unexpected identifier \"truex\"");
+    assert!(Tokenizer::new("false_").tokenize().is_err());
+    assert!(Tokenizer::new("null$").tokenize().is_err());
+    assert!(Tokenizer::new("tru").tokenize().is_err());
+    assert!(Tokenizer::new("nu日").tokenize().is_err());
+
+    // 允许未加引号的 key 时，这些单词是普通的标识符
+    let options = ParseOptions {
+      allow_unquoted_keys: true,
+      ..ParseOptions::default()
+    };
+    let tokens = Tokenizer::with_options("{nullable: true, null1: null}", options)
+      .tokenize()
+      .unwrap();
+    assert!(matches!(&tokens[1], Token::Identifier(t) if t.value == "nullable"));
+    assert!(matches!(&tokens[5], Token::Identifier(t) if t.value == "null1"));
+    assert!(matches!(&tokens[7], Token::Null(_)));
+
+    // NaN、Infinity 同样按关键字处理
+    let options = ParseOptions {
+      allow_non_finite: true,
+      ..ParseOptions::default()
+    };
+    for input in ["NaNx", "Infinityy", "-Infinity_a", "[NaN$]"] {
+      assert!(
+        Tokenizer::with_options(input, options.clone())
+          .tokenize()
+          .is_err(),
+        "input: {:?}",
+        input
+      );
+    }
+    let err = Tokenizer::with_options("NaNx", options.clone())
+      .tokenize()
+      .unwrap_err();
+    assert_eq!(err.message, "Unexpected identifier \"NaNx\"");
+
+    let options = ParseOptions {
+      allow_unquoted_keys: true,
+      ..options
+    };
+    let tokens = Tokenizer::with_options("{NaNx: Infinity}", options)
+      .tokenize()
+      .unwrap();
+    assert!(matches!(&tokens[1], Token::Identifier(t) if t.value == "NaNx"));
+    assert!(matches!(&tokens[3], Token::Number(t) if t.value == f64::INFINITY));
+  }
+
+  #[test]
+  fn test_length_limits() {
+    let options = ParseOptions {
+      max_string_len: Some(5),
+      max_total_len: Some(20),
+      ..ParseOptions::default()
+    };
+
+    let tokens = Tokenizer::with_options("[\"abcde\", \"é\\n\"]", options.clone())
+      .tokenize()
+      .unwrap();
+    assert_eq!(tokens.len(), 5);
+
+    let err = Tokenizer::with_options("[\"abcdef\"]", options.clone())
+      .tokenize()
+      .unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::LimitExceeded);
+    assert_eq!(err.message, "String exceeds the maximum length of 5 bytes");
+    assert_eq!(err.span.unwrap().start.offset, 1);
+    // 多字节字符按字节计算
+    assert!(Tokenizer::with_options("\"ééé\"", options.clone())
+      .tokenize()
+      .is_err());
+
+    let err = Tokenizer::with_options(&format!("[{}]", "1,".repeat(10)), options)
+      .tokenize()
+      .unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::LimitExceeded);
+    assert_eq!(err.span, None);
+  }
+
+  #[test]
+  fn test_number_overflow() {
+    let huge = "9".repeat(400);
+
+    // 默认静默变成 inf
+    for input in ["1e400", huge.as_str()] {
+      let tokens = Tokenizer::new(input).tokenize().unwrap();
+      assert!(matches!(&tokens[0], Token::Number(t) if t.value == f64::INFINITY));
+    }
+    let tokens = Tokenizer::new("-1e400").tokenize().unwrap();
+    assert!(matches!(&tokens[0], Token::Number(t) if t.value == f64::NEG_INFINITY));
+
+    let options = ParseOptions {
+      reject_number_overflow: true,
+      allow_non_finite: true,
+      ..ParseOptions::default()
+    };
+
+    let err = Tokenizer::with_options("[1e400]", options.clone())
+      .tokenize()
+      .unwrap_err();
+    assert_eq!(err.message, "Number 1e400 is out of range");
+    let span = err.span.unwrap();
+    assert_eq!((span.start.offset, span.end.offset), (1, 6));
+
+    let err = Tokenizer::with_options(&huge, options.clone())
+      .tokenize()
+      .unwrap_err();
+    assert_eq!(err.span.unwrap().end.offset, 400);
+
+    // 下溢、显式的 Infinity 不算溢出
+    let tokens = Tokenizer::with_options("[1e-400, 1.7976931348623157e308, -Infinity]", options)
+      .tokenize()
+      .unwrap();
+    assert!(matches!(&tokens[1], Token::Number(t) if t.value == 0.0));
+    assert!(matches!(&tokens[3], Token::Number(t) if t.value == f64::MAX));
+    assert!(matches!(&tokens[5], Token::Number(t) if t.value == f64::NEG_INFINITY));
+  }
+
+  #[test]
+  fn test_tab_width() {
+    let options = ParseOptions {
+      tab_width: 4,
+      ..ParseOptions::default()
+    };
+    let tokens = Tokenizer::with_options("\t[\t1,  \t2]", options.clone())
+      .tokenize()
+      .unwrap();
+    let starts = tokens
+      .iter()
+      .map(|token| {
+        let start = &token.get_span().start;
+        (start.column, start.offset)
+      })
+      .collect::<Vec<(usize, usize)>>();
+    // offset 仍按字符计算
+    assert_eq!(starts, [(5, 1), (9, 3), (10, 4), (17, 8), (18, 9)]);
+
+    // 默认 tab 只占一列
+    let tokens = Tokenizer::new("\t\t1").tokenize().unwrap();
+    assert_eq!(tokens[0].get_span().start.column, 3);
+
+    let source = "[\n\t\tnull,\n\t\ttrue false]";
+    let err = crate::Json::parse_with_options(source, &options).unwrap_err();
+    assert_eq!(err.to_string(), "Unexpected boolean at line 3, column 14");
+    // snippet 按字符对齐，不受 tab_width 影响
+    assert_eq!(
+      err.snippet(source),
+      "3 | \t\ttrue false]\n  | \t\t     ^^^^^"
+    );
+  }
+
+  #[test]
+  fn test_resume_at() {
+    let input = "{\"日本\": [1, true],\n  \"b\": \"é\", \"c\": null}";
+    let full = Tokenizer::new(input).tokenize().unwrap();
+
+    let mut tokenizer = Tokenizer::new(input);
+    let mut tokens = vec![];
+    for _ in 0..full.len() / 2 {
+      tokens.push(tokenizer.next_token().unwrap().unwrap());
+    }
+    let loc = tokenizer.loc();
+    let byte_pos = tokenizer.byte_pos();
+    assert_eq!(tokenizer.remaining(), &input[byte_pos..]);
+
+    let mut tokenizer = Tokenizer::resume_at(input, loc);
+    assert_eq!(tokenizer.byte_pos(), byte_pos);
+    tokens.extend(tokenizer.tokenize().unwrap());
+
+    assert_eq!(tokens, full);
+    assert_eq!(tokenizer.remaining(), "");
+  }
+
+  #[test]
+  fn test_display() {
+    let options = ParseOptions {
+      allow_comments: true,
+      allow_unquoted_keys: true,
+      allow_single_quotes: true,
+      ..ParseOptions::default()
+    };
+    let tokens = Tokenizer::with_trivia(
+      "{k: ['foo', \"b\\\"r\", -3.140, true, null]} // end",
+      options,
+    )
+    .tokenize()
+    .unwrap();
+    let texts: Vec<_> = tokens.iter().map(|token| token.to_string()).collect();
+
+    assert_eq!(
+      texts,
+      [
+        "{",
+        "identifier k",
+        ":",
+        "whitespace",
+        "[",
+        "string 'foo'",
+        ",",
+        "whitespace",
+        "string \"b\\\"r\"",
+        ",",
+        "whitespace",
+        "number -3.140",
+        ",",
+        "whitespace",
+        "boolean true",
+        ",",
+        "whitespace",
+        "null",
+        "]",
+        "}",
+        "whitespace",
+        "comment // end",
+      ]
+    );
+  }
 }
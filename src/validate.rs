@@ -0,0 +1,272 @@
+use std::collections::HashSet;
+
+use crate::{
+  error::ParseError,
+  options::{DuplicateKeyPolicy, ParseOptions},
+  parser::{
+    array_limit_exceeded, depth_exceeded, duplicate_key, object_limit_exceeded, parse_string,
+    top_level_not_composite, unexpected_token, ArrayState, ObjectState, Step,
+  },
+  span::Span,
+  tokenizer::{Token, Tokenizer},
+};
+
+// 与 parser 中的 Frame 对应，但只记录计数，开启 duplicate_keys 时才保存已出现的 key
+enum Frame {
+  Object {
+    start_span: Span,
+    state: ObjectState,
+    len: usize,
+    keys: Option<HashSet<String>>,
+    // 已读到的 key（只在需要判断重复时保存）和它的 span
+    key: Option<(Option<String>, Span)>,
+  },
+  Array {
+    start_span: Span,
+    state: ArrayState,
+    len: usize,
+  },
+}
+
+// 边切分边校验，不保存 token 列表，也不构建 Ast，接受的输入和报告的错误与 Parser::parse 相同
+pub(crate) fn validate(input: &str, options: &ParseOptions) -> Result<(), ParseError> {
+  let mut validator = Validator {
+    tokenizer: Tokenizer::with_options(input, options.clone()),
+    options,
+    last_span: None,
+    lex_error: false,
+  };
+  validator.tokenizer.check_total_len()?;
+
+  let result = validator.run();
+  if validator.lex_error {
+    return result;
+  }
+
+  // parse 会先切分完整个输入，后面的词法错误优先于语法错误
+  while validator.tokenizer.next_token()?.is_some() {}
+  result
+}
+
+struct Validator<'a, 'o> {
+  tokenizer: Tokenizer<'a>,
+  options: &'o ParseOptions,
+  // 最后一个 token 的 span，用于 EOF 错误
+  last_span: Option<Span>,
+  lex_error: bool,
+}
+
+impl<'a, 'o> Validator<'a, 'o> {
+  fn next(&mut self) -> Result<Option<Token>, ParseError> {
+    let token = self.tokenizer.next_token().map_err(|error| {
+      self.lex_error = true;
+      error
+    })?;
+
+    if let Some(token) = &token {
+      self.last_span = Some(token.get_span().clone());
+    }
+    Ok(token)
+  }
+
+  fn run(&mut self) -> Result<(), ParseError> {
+    let Some(token) = self.next()? else {
+      return Err(ParseError::empty_input());
+    };
+
+    let composite = matches!(token, Token::LeftBrace(_) | Token::LeftBracket(_));
+    let mut stack = vec![];
+    // 刚校验完的值的 span，交给栈顶的 object、array
+    let mut value = self.begin_value(token, &mut stack)?;
+
+    loop {
+      if let Some(span) = value.take() {
+        match stack.last_mut() {
+          None if self.options.require_top_level_composite && !composite => {
+            return Err(top_level_not_composite(&span));
+          }
+          None => return Ok(()),
+          Some(Frame::Object {
+            state,
+            len,
+            keys,
+            key,
+            ..
+          }) => {
+            let (name, key_span) = key.take().unwrap();
+            let mut added = true;
+
+            if let (Some(keys), Some(name)) = (keys.as_mut(), name) {
+              if keys.contains(&name) {
+                if let Some(DuplicateKeyPolicy::Error) = self.options.duplicate_keys {
+                  return Err(duplicate_key(&name, &key_span));
+                }
+                added = false;
+              } else {
+                keys.insert(name);
+              }
+            }
+
+            if added {
+              *len += 1;
+            }
+            if let Some(max) = self.options.max_object_entries.filter(|max| *len > *max) {
+              return Err(object_limit_exceeded(max, &key_span.merge(&span)));
+            }
+            *state = ObjectState::Property;
+          }
+          Some(Frame::Array { state, len, .. }) => {
+            if let Some(max) = self.options.max_array_entries.filter(|max| *len >= *max) {
+              return Err(array_limit_exceeded(max, &span));
+            }
+            *len += 1;
+            *state = ArrayState::Value;
+          }
+        }
+        continue;
+      }
+
+      let Some(token) = self.next()? else {
+        return Err(ParseError::eof(self.last_span.clone()));
+      };
+      let allow_trailing_commas = self.options.allow_trailing_commas;
+
+      match stack.last_mut().unwrap() {
+        Frame::Object {
+          state, keys, key, ..
+        } => match state.step(&token, allow_trailing_commas) {
+          Step::Close => value = Some(close(&mut stack, token.get_span())),
+          Step::Key => {
+            let name = match &token {
+              Token::String(token) => {
+                parse_string(&token.value, &token.span, self.options.allow_single_quotes)?
+              }
+              Token::Identifier(token) => token.value.clone(),
+              _ => return Err(unexpected_token(&token)),
+            };
+            *key = Some((keys.as_ref().map(|_| name), token.get_span().clone()));
+            *state = ObjectState::Key;
+          }
+          Step::Colon => *state = ObjectState::Colon,
+          Step::Comma => *state = ObjectState::Comma,
+          Step::Value => value = self.begin_value(token, &mut stack)?,
+          Step::Unexpected => return Err(unexpected_token(&token)),
+        },
+        Frame::Array { state, .. } => match state.step(&token, allow_trailing_commas) {
+          Step::Close => value = Some(close(&mut stack, token.get_span())),
+          Step::Value => value = self.begin_value(token, &mut stack)?,
+          Step::Comma => *state = ArrayState::Comma,
+          _ => return Err(unexpected_token(&token)),
+        },
+      }
+    }
+  }
+
+  // 遇到 { 或 [ 时压栈并返回 None，否则校验字面量并返回它的 span
+  fn begin_value(&self, token: Token, stack: &mut Vec<Frame>) -> Result<Option<Span>, ParseError> {
+    if let Token::LeftBrace(_) | Token::LeftBracket(_) = token {
+      if let Some(max) = self.options.max_depth.filter(|max| stack.len() >= *max) {
+        return Err(depth_exceeded(max, token.get_span()));
+      }
+    }
+
+    match token {
+      Token::LeftBrace(token) => {
+        stack.push(Frame::Object {
+          start_span: token.span,
+          state: ObjectState::LeftBrace,
+          len: 0,
+          keys: self.options.duplicate_keys.map(|_| HashSet::new()),
+          key: None,
+        });
+        Ok(None)
+      }
+      Token::LeftBracket(token) => {
+        stack.push(Frame::Array {
+          start_span: token.span,
+          state: ArrayState::LeftBracket,
+          len: 0,
+        });
+        Ok(None)
+      }
+      Token::String(token) => {
+        parse_string(&token.value, &token.span, self.options.allow_single_quotes)?;
+        Ok(Some(token.span))
+      }
+      Token::Identifier(token) if self.options.allow_bare_strings => Ok(Some(token.span)),
+      Token::Number(_) | Token::Boolean(_) | Token::Null(_) => Ok(Some(token.get_span().clone())),
+      _ => Err(unexpected_token(&token)),
+    }
+  }
+}
+
+fn close(stack: &mut Vec<Frame>, end_span: &Span) -> Span {
+  match stack.pop().unwrap() {
+    Frame::Object { start_span, .. } | Frame::Array { start_span, .. } => {
+      start_span.merge(end_span)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Json;
+
+  #[test]
+  fn test_same_result_as_parse() {
+    let relaxed = ParseOptions {
+      allow_single_quotes: true,
+      allow_bare_strings: true,
+      allow_non_finite: true,
+      allow_comments: true,
+      allow_trailing_commas: true,
+      ..ParseOptions::default()
+    };
+    let limited = |duplicate_keys| ParseOptions {
+      duplicate_keys,
+      max_object_entries: Some(2),
+      max_array_entries: Some(2),
+      max_depth: Some(2),
+      require_top_level_composite: true,
+      ..ParseOptions::default()
+    };
+    let options = [
+      ParseOptions::default(),
+      relaxed,
+      limited(None),
+      limited(Some(DuplicateKeyPolicy::FirstWins)),
+      limited(Some(DuplicateKeyPolicy::LastWins)),
+      limited(Some(DuplicateKeyPolicy::Error)),
+    ];
+
+    for input in [
+      "",
+      "1 2",
+      "1 @",
+      "[1 2, @]",
+      "{\"a\": 1, \"a\": [1 2]}",
+      "{\"a\": 1, \"a\": 2, \"b\": 3}",
+      "{\"a\": 1, \"b\": 2, \"c\": 3}",
+      "[1, 2, [3]]",
+      "[[[]]]",
+      "[{\"a\": {}}]",
+      "\"top\"",
+      "{a: 'b', c: [NaN, x,], // c\n}",
+      "{\"a\" 1}",
+      "{\"\\q\": 1}",
+      "[1, 2",
+      "{\"a\":",
+    ] {
+      for options in &options {
+        assert_eq!(
+          validate(input, options),
+          Json::parse_with_options(input, options).map(|_| ()),
+          "input: {:?}, options: {:?}",
+          input,
+          options
+        );
+      }
+    }
+  }
+}
@@ -1,58 +1,356 @@
+use std::ops::ControlFlow;
+
 use crate::{
   parser::{
     ArrayAst, BoolAst, IdentifierAst, NullAst, NumberAst, ObjectAst, PropertyAst, StringAst,
   },
+  pointer::PathSegment,
   Json,
 };
 
+// 返回 ControlFlow::Break 可以提前终止遍历
 pub trait Visit {
-  fn visit_json(&mut self, ast: &mut Json) {
+  // 拿到的是整个节点，可以用 *ast = ... 或 Ast::take 整体替换；
+  // 重写时调用 walk_json 继续按类型分发
+  fn visit_json(&mut self, ast: &mut Json) -> ControlFlow<()> {
+    walk_json(self, ast)
+  }
+
+  fn visit_string(&mut self, _ast: &mut StringAst) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
+
+  fn visit_number(&mut self, _ast: &mut NumberAst) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
+
+  fn visit_boolean(&mut self, _ast: &mut BoolAst) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
+
+  fn visit_null(&mut self, _ast: &mut NullAst) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
+
+  fn visit_object(&mut self, ast: &mut ObjectAst) -> ControlFlow<()> {
+    for property in ast.value.iter_mut() {
+      self.visit_property(property)?;
+    }
+
+    ControlFlow::Continue(())
+  }
+
+  // key 和 value 一起拿到，重写 key 时覆盖这个方法，再调用 walk_property 继续遍历
+  fn visit_property(&mut self, ast: &mut PropertyAst) -> ControlFlow<()> {
+    walk_property(self, ast)
+  }
+
+  fn visit_identifier(&mut self, ast: &mut IdentifierAst) -> ControlFlow<()> {
+    self.visit_string(&mut ast.value)
+  }
+
+  // 与 visit_array_item 对应，数组元素带下标，属性值带 key
+  fn visit_property_value(&mut self, _key: &str, ast: &mut Json) -> ControlFlow<()> {
+    self.visit_json(ast)
+  }
+
+  fn visit_array(&mut self, ast: &mut ArrayAst) -> ControlFlow<()> {
+    for (index, item) in ast.value.iter_mut().enumerate() {
+      self.visit_array_item(index, item)?;
+    }
+
+    ControlFlow::Continue(())
+  }
+
+  fn visit_array_item(&mut self, _index: usize, ast: &mut Json) -> ControlFlow<()> {
+    self.visit_json(ast)
+  }
+}
+
+// Visit::visit_json 的默认实现
+pub fn walk_json<V: Visit + ?Sized>(visitor: &mut V, ast: &mut Json) -> ControlFlow<()> {
+  match ast {
+    Json::String(ast) => visitor.visit_string(ast),
+    Json::Number(ast) => visitor.visit_number(ast),
+    Json::Boolean(ast) => visitor.visit_boolean(ast),
+    Json::Null(ast) => visitor.visit_null(ast),
+    Json::Object(ast) => visitor.visit_object(ast),
+    Json::Property(ast) => visitor.visit_property(ast),
+    Json::Identifier(ast) => visitor.visit_identifier(ast),
+    Json::Array(ast) => visitor.visit_array(ast),
+  }
+}
+
+// Visit::visit_property 的默认实现
+pub fn walk_property<V: Visit + ?Sized>(visitor: &mut V, ast: &mut PropertyAst) -> ControlFlow<()> {
+  visitor.visit_identifier(&mut ast.key)?;
+  visitor.visit_property_value(&ast.key.value.value, &mut ast.value)
+}
+
+impl Json {
+  // 对树中所有名为 key 的属性的值调用 f，f 修改后的值里如果还有同名属性也会被处理
+  pub fn visit_key(&mut self, key: &str, mut f: impl FnMut(&mut Json)) {
+    self.walk_mut(|ast| {
+      if let Json::Object(object) = ast {
+        for property in object.value.iter_mut() {
+          if property.key_str() == key {
+            f(&mut property.value);
+          }
+        }
+      }
+    });
+  }
+}
+
+// 与 Visit 类似，但每个回调都会收到当前节点的路径
+pub trait VisitWithPath {
+  fn visit_json(&mut self, path: &mut Vec<PathSegment>, ast: &mut Json) -> ControlFlow<()> {
     match ast {
-      Json::String(ast) => self.visit_string(ast),
-      Json::Number(ast) => self.visit_number(ast),
-      Json::Boolean(ast) => self.visit_boolean(ast),
-      Json::Null(ast) => self.visit_null(ast),
-      Json::Object(ast) => self.visit_object(ast),
-      Json::Property(ast) => self.visit_property(ast),
-      Json::Identifier(ast) => self.visit_identifier(ast),
-      Json::Array(ast) => self.visit_array(ast),
+      Json::String(ast) => self.visit_string(path, ast),
+      Json::Number(ast) => self.visit_number(path, ast),
+      Json::Boolean(ast) => self.visit_boolean(path, ast),
+      Json::Null(ast) => self.visit_null(path, ast),
+      Json::Object(ast) => self.visit_object(path, ast),
+      Json::Array(ast) => self.visit_array(path, ast),
+      Json::Property(_) | Json::Identifier(_) => ControlFlow::Continue(()),
     }
   }
 
-  fn visit_string(&mut self, _ast: &mut StringAst) {}
+  fn visit_string(&mut self, _path: &[PathSegment], _ast: &mut StringAst) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
 
-  fn visit_number(&mut self, _ast: &mut NumberAst) {}
+  fn visit_number(&mut self, _path: &[PathSegment], _ast: &mut NumberAst) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
 
-  fn visit_boolean(&mut self, _ast: &mut BoolAst) {}
+  fn visit_boolean(&mut self, _path: &[PathSegment], _ast: &mut BoolAst) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
 
-  fn visit_null(&mut self, _ast: &mut NullAst) {}
+  fn visit_null(&mut self, _path: &[PathSegment], _ast: &mut NullAst) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+  }
 
-  fn visit_object(&mut self, ast: &mut ObjectAst) {
+  fn visit_object(&mut self, path: &mut Vec<PathSegment>, ast: &mut ObjectAst) -> ControlFlow<()> {
     for property in ast.value.iter_mut() {
-      self.visit_property(property);
+      path.push(PathSegment::Key(property.key.value.value.clone()));
+      self.visit_json(path, &mut property.value)?;
+      path.pop();
     }
+
+    ControlFlow::Continue(())
   }
 
-  fn visit_property(&mut self, ast: &mut PropertyAst) {
-    self.visit_identifier(&mut ast.key);
-    self.visit_property_value(&mut ast.value);
+  fn visit_array(&mut self, path: &mut Vec<PathSegment>, ast: &mut ArrayAst) -> ControlFlow<()> {
+    for (index, item) in ast.value.iter_mut().enumerate() {
+      path.push(PathSegment::Index(index));
+      self.visit_json(path, item)?;
+      path.pop();
+    }
+
+    ControlFlow::Continue(())
   }
+}
+
+pub fn visit_with_path<V: VisitWithPath>(visitor: &mut V, ast: &mut Json) -> ControlFlow<()> {
+  visitor.visit_json(&mut vec![], ast)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::pointer::to_pointer;
+
+  use super::*;
+
+  #[test]
+  fn test_break() {
+    struct FindString {
+      found: Option<String>,
+      visited: usize,
+    }
+
+    impl Visit for FindString {
+      fn visit_string(&mut self, ast: &mut StringAst) -> ControlFlow<()> {
+        self.visited += 1;
+
+        if ast.value.starts_with('b') {
+          self.found = Some(ast.value.clone());
+          return ControlFlow::Break(());
+        }
+
+        ControlFlow::Continue(())
+      }
+    }
 
-  fn visit_identifier(&mut self, ast: &mut IdentifierAst) {
-    self.visit_string(&mut ast.value);
+    let mut json = Json::parse("[\"a\", {\"x\": \"bar\"}, \"baz\", \"c\"]").unwrap();
+    let mut visitor = FindString {
+      found: None,
+      visited: 0,
+    };
+
+    assert_eq!(visitor.visit_json(&mut json), ControlFlow::Break(()));
+    assert_eq!(visitor.found, Some("bar".to_string()));
+    // "a"、key "x"、"bar"，后面的 "baz"、"c" 不再访问
+    assert_eq!(visitor.visited, 3);
   }
 
-  fn visit_property_value(&mut self, ast: &mut Json) {
-    self.visit_json(ast);
+  #[test]
+  fn test_replace_nodes() {
+    // 把每个数字换成 {"$num": 数字的两倍}
+    struct WrapNumbers;
+
+    impl Visit for WrapNumbers {
+      fn visit_json(&mut self, ast: &mut Json) -> ControlFlow<()> {
+        match ast.as_f64() {
+          Some(value) => {
+            let old = ast.replace(
+              vec![("$num".to_string(), Json::from(value * 2.0))]
+                .into_iter()
+                .collect(),
+            );
+            assert_eq!(old.as_f64(), Some(value));
+            ControlFlow::Continue(())
+          }
+          None => walk_json(self, ast),
+        }
+      }
+    }
+
+    let mut json = Json::parse("{\"a\": [1, \"x\", {\"b\": 2.5}], \"c\": 3}").unwrap();
+    let _ = WrapNumbers.visit_json(&mut json);
+
+    assert_eq!(
+      json.to_string().unwrap(),
+      "{\"a\":[{\"$num\":2},\"x\",{\"b\":{\"$num\":5}}],\"c\":{\"$num\":6}}"
+    );
   }
 
-  fn visit_array(&mut self, ast: &mut ArrayAst) {
-    for item in ast.value.iter_mut() {
-      self.visit_array_item(item);
+  #[test]
+  fn test_rename_keys() {
+    struct UppercaseKeys;
+
+    impl Visit for UppercaseKeys {
+      fn visit_property(&mut self, ast: &mut PropertyAst) -> ControlFlow<()> {
+        let key = ast.key.value.value.to_uppercase();
+        ast.key.set_value(&key);
+        walk_property(self, ast)
+      }
     }
+
+    let mut json = Json::parse("{\"a\": {\"b\\u00e9\": [{\"c\": 1}]}, \"d\": \"e\"}").unwrap();
+    let _ = UppercaseKeys.visit_json(&mut json);
+
+    assert_eq!(
+      json.to_string().unwrap(),
+      "{\"A\":{\"BÉ\":[{\"C\":1}]},\"D\":\"e\"}"
+    );
   }
 
-  fn visit_array_item(&mut self, ast: &mut Json) {
-    self.visit_json(ast);
+  #[test]
+  fn test_visit_with_path() {
+    struct Leaves(Vec<String>);
+
+    impl VisitWithPath for Leaves {
+      fn visit_string(&mut self, path: &[PathSegment], _ast: &mut StringAst) -> ControlFlow<()> {
+        self.0.push(to_pointer(path));
+        ControlFlow::Continue(())
+      }
+
+      fn visit_number(&mut self, path: &[PathSegment], _ast: &mut NumberAst) -> ControlFlow<()> {
+        self.0.push(to_pointer(path));
+        ControlFlow::Continue(())
+      }
+
+      fn visit_null(&mut self, path: &[PathSegment], _ast: &mut NullAst) -> ControlFlow<()> {
+        self.0.push(to_pointer(path));
+        ControlFlow::Continue(())
+      }
+    }
+
+    let mut json =
+      Json::parse("{\"a\": [{\"b\": 1}, \"x\", []], \"c/d\": {\"e\": null}, \"f\": 2}").unwrap();
+    let mut visitor = Leaves(vec![]);
+    let _ = visit_with_path(&mut visitor, &mut json);
+
+    assert_eq!(visitor.0, vec!["/a/0/b", "/a/1", "/c~1d/e", "/f"]);
+  }
+
+  #[test]
+  fn test_visit_number_raw() {
+    struct RedundantNumbers {
+      raw: Vec<String>,
+    }
+
+    impl Visit for RedundantNumbers {
+      fn visit_number(&mut self, ast: &mut NumberAst) -> ControlFlow<()> {
+        self.raw.push(ast.raw.clone().unwrap());
+        ControlFlow::Continue(())
+      }
+    }
+
+    let mut json =
+      Json::parse("{\"a\": [1.0, 1e0, 1], \"b\": {\"c\": -0.50}, \"d\": 1E2}").unwrap();
+    let mut visitor = RedundantNumbers { raw: vec![] };
+    let _ = visitor.visit_json(&mut json);
+
+    assert_eq!(visitor.raw, ["1.0", "1e0", "1", "-0.50", "1E2"]);
+  }
+
+  #[test]
+  fn test_item_index_and_key() {
+    struct Items(Vec<String>);
+
+    impl Visit for Items {
+      fn visit_array_item(&mut self, index: usize, ast: &mut Json) -> ControlFlow<()> {
+        self.0.push(format!("{} {}", index, ast.type_name()));
+        self.visit_json(ast)
+      }
+
+      fn visit_property_value(&mut self, key: &str, ast: &mut Json) -> ControlFlow<()> {
+        self.0.push(format!("{} {}", key, ast.type_name()));
+        self.visit_json(ast)
+      }
+    }
+
+    let mut json = Json::parse("[1, \"a\", {\"k\": [null]}, true]").unwrap();
+    let mut visitor = Items(vec![]);
+    let _ = visitor.visit_json(&mut json);
+
+    assert_eq!(
+      visitor.0,
+      [
+        "0 number",
+        "1 string",
+        "2 object",
+        "k array",
+        "0 null",
+        "3 boolean"
+      ]
+    );
+  }
+
+  #[test]
+  fn test_visit_key() {
+    let mut json = Json::parse(
+      "{\"url\": \"HTTP://A.com/\", \"links\": [{\"url\": \"http://b.com\"}, {\"href\": \"X\"}], \
+       \"meta\": {\"url\": null, \"urls\": \"Y\"}}",
+    )
+    .unwrap();
+    let mut count = 0;
+
+    json.visit_key("url", |value| {
+      count += 1;
+      if let Json::String(url) = value {
+        url.value = url.value.to_lowercase().trim_end_matches('/').to_string();
+      }
+    });
+
+    assert_eq!(count, 3);
+    assert_eq!(
+      json.to_string().unwrap(),
+      "{\"url\":\"http://a.com\",\"links\":[{\"url\":\"http://b.com\"},{\"href\":\"X\"}],\
+       \"meta\":{\"url\":null,\"urls\":\"Y\"}}"
+    );
   }
 }